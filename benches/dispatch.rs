@@ -0,0 +1,129 @@
+// Benchmarks for the message bus, on boards from expert up to a hundred thousand tiles. Each one plays through
+// Game, so a message goes from the front end through the dispatcher to the sprites, the way the front ends send
+// them. Run them with:
+// cargo bench --no-default-features
+use std::rc::Rc;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+use minesweeperlib::{ChannelMessage, FirstClick, Game, HeadlessContext, Layout};
+use minesweeperlib::{MouseAction, MouseButton, MouseEventData, Options, RendererContext};
+
+const MOVES: u64 = 1000;
+
+// expert, then about ten and a hundred thousand tiles
+fn boards() -> Vec<Options> {
+    [(16, 30, 99), (100, 100, 1600), (320, 320, 16000)]
+        .iter()
+        .map(|(rows, columns, mines)| {
+            let options = Options::custom(*rows, *columns, *mines).unwrap();
+            options.with_first_click(FirstClick::Opening).with_seed(1)
+        })
+        .collect()
+}
+
+fn mouse(layout: &Layout, row: i16, column: i16, action: MouseAction) -> ChannelMessage {
+    let tile = layout.grid_tile(layout.options.index(row, column));
+    ChannelMessage::MouseEvent(MouseEventData {
+        x: tile.left(),
+        y: tile.top(),
+        mouse_btn: MouseButton::Left,
+        action: action,
+    })
+}
+
+fn context(layout: Layout) -> Rc<Box<dyn RendererContext>> {
+    Rc::new(Box::new(HeadlessContext::new(layout)) as Box<dyn RendererContext>)
+}
+
+// the first reveal, which places the mines, opens an area and lets the button and counters know
+fn reveal(c: &mut Criterion) {
+    let mut group = c.benchmark_group("reveal");
+    group.sample_size(10);
+    for options in boards() {
+        let layout = Layout::new(options);
+        let center = (options.rows / 2, options.columns / 2);
+        group.throughput(Throughput::Elements(options.tiles() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(options.tiles()),
+            &layout,
+            |b, layout| {
+                b.iter_batched(
+                    || Game::new(*layout),
+                    |mut game| {
+                        let message = mouse(layout, center.0, center.1, MouseAction::Click);
+                        game.send(message);
+                        game
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+// the mouse moving while a tile is held, the messages go through the bus without changing the board
+fn moves(c: &mut Criterion) {
+    let mut group = c.benchmark_group("moves");
+    group.throughput(Throughput::Elements(MOVES));
+    for options in boards() {
+        let layout = Layout::new(options);
+        let mut game = Game::new(layout);
+        game.send(mouse(&layout, 0, 0, MouseAction::Press));
+        let sender = game.get_sender();
+        group.bench_function(BenchmarkId::from_parameter(options.tiles()), |b| {
+            b.iter(|| {
+                for index in 0..MOVES {
+                    let column = (index % 2) as i16;
+                    sender
+                        .send(mouse(&layout, 0, column, MouseAction::Move))
+                        .unwrap();
+                }
+                game.run_until_quiescent();
+            })
+        });
+    }
+    group.finish();
+}
+
+// a render after the held tile is pushed in or out again, and one where nothing changed
+fn render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    for options in boards() {
+        let layout = Layout::new(options);
+        let mut game = Game::new(layout);
+        let context = context(layout);
+        let sender = game.get_sender();
+        sender
+            .send(mouse(&layout, 0, 0, MouseAction::Press))
+            .unwrap();
+        sender
+            .send(ChannelMessage::Render(Rc::clone(&context)))
+            .unwrap();
+        game.run_until_quiescent();
+        let tiles = options.tiles();
+        group.throughput(Throughput::Elements(tiles as u64));
+        group.bench_function(BenchmarkId::new("changed", tiles), |b| {
+            b.iter(|| {
+                for column in [1, 0].iter() {
+                    sender
+                        .send(mouse(&layout, 0, *column, MouseAction::Move))
+                        .unwrap();
+                    game.send(ChannelMessage::Render(Rc::clone(&context)));
+                    context.end_rendering();
+                }
+            })
+        });
+        group.bench_function(BenchmarkId::new("unchanged", tiles), |b| {
+            b.iter(|| {
+                game.send(ChannelMessage::Render(Rc::clone(&context)));
+                context.end_rendering();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, reveal, moves, render);
+criterion_main!(benches);
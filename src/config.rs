@@ -1,5 +1,5 @@
 mod layout;
-pub use layout::{Layout, BEGINNER_LAYOUT, EXPERT_LAYOUT, INTERMEDIATE_LAYOUT};
+pub use layout::Layout;
 
 mod options;
-pub use options::Options;
+pub use options::{FirstClick, Options, BEGINNER_OPTIONS, EXPERT_OPTIONS, INTERMEDIATE_OPTIONS};
@@ -1,89 +1,90 @@
 use super::Options;
 use crate::sprites::Rect;
 
-// constants used in GUI layout
-const GRID_LEFT: i32 = 15;
-const GRID_TOP: i32 = 81;
+// the sizes of the images
 const TILE_SIDE: u32 = 20;
 const DIGIT_PANEL_WIDTH: u32 = 65;
 const DIGIT_PANEL_HEIGHT: u32 = 37;
-const TIMER_TOP: u32 = 21;
-const FLAG_TOP: u32 = 21;
-const FACE_TOP: u32 = 19;
+const DIGIT_WIDTH: u32 = 19;
+const DIGIT_HEIGHT: u32 = 33;
 const FACE_WIDTH: u32 = 42;
 const FACE_HEIGHT: u32 = 42;
 
-const BEGINNER_HEIGHT: u32 = 276;
-const BEGINNER_WIDTH: u32 = 210;
-const INTERMEDIATE_HEIGHT: u32 = 416;
-const INTERMEDIATE_WIDTH: u32 = 350;
-const EXPERT_HEIGHT: u32 = 416;
-const EXPERT_WIDTH: u32 = 630;
+// the frame, which is drawn
+const MARGIN: u32 = 12; //< from the edge of the window to the header and the grid
+const BEVEL: u32 = 3; //< the width of the raised and sunken edges
+const HEADER_HEIGHT: u32 = 49; //< inside the bevel
+const HEADER_GAP: u32 = 11; //< between the header and the grid
+const PANEL_INSET: u32 = 5; //< from the inside of the header to the digit panels
+const MIN_PANEL_INSET: u32 = 1; //< when there isn't room for PANEL_INSET
+const MIN_FACE_GAP: u32 = 3; //< between the digit panels and the face
 
-const DIGIT_WIDTH: u32 = 19;
-const DIGIT_HEIGHT: u32 = 33;
 const DIGIT_PANEL_HORZ_MARGIN: u32 = (DIGIT_PANEL_WIDTH - (3 * DIGIT_WIDTH)) / 4;
 const DIGIT_PANEL_VERT_MARGIN: u32 = (DIGIT_PANEL_HEIGHT - DIGIT_HEIGHT) / 2;
 
-const BEGINNER_DIGIT_PANEL_OFFSET: u32 = 16;
-const DEFAULT_DIGIT_PANEL_OFFSET: u32 = 20;
-
 /**
- * LayoutConstants is an internal structure holding constants which vary based upon the skill level.
+ * Layout holds all of the information and provides all of the layout information for minesweeper. It is computed
+ * from the Options when the game starts: the grid sets the size of the window, and the header, face and digit
+ * panels are placed around it, so any size of board can be laid out.
  */
-#[derive(Debug)]
-struct LayoutConstants {
-    height: u32,
+#[derive(Debug, Copy, Clone)]
+pub struct Layout {
+    pub options: Options,
     width: u32,
+    height: u32,
+    header: Rect,
+    grid: Rect,
+    face: Rect,
     panel_offset: u32,
 }
 
-const BEGINNER_LAYOUT_CONSTANTS: LayoutConstants = LayoutConstants {
-    height: BEGINNER_HEIGHT,
-    width: BEGINNER_WIDTH,
-    panel_offset: BEGINNER_DIGIT_PANEL_OFFSET,
-};
-const INTERMEDIATE_LAYOUT_CONSTANTS: LayoutConstants = LayoutConstants {
-    height: INTERMEDIATE_HEIGHT,
-    width: INTERMEDIATE_WIDTH,
-    panel_offset: DEFAULT_DIGIT_PANEL_OFFSET,
-};
-const EXPERT_LAYOUT_CONSTANTS: LayoutConstants = LayoutConstants {
-    height: EXPERT_HEIGHT,
-    width: EXPERT_WIDTH,
-    panel_offset: DEFAULT_DIGIT_PANEL_OFFSET,
-};
+impl Layout {
+    pub fn new(options: Options) -> Self {
+        let grid_width = options.columns as u32 * TILE_SIDE;
+        let grid_height = options.rows as u32 * TILE_SIDE;
+        let inside = MARGIN + BEVEL;
 
-/**
- * Layout holds all of the information and provides all of the layout information for minesweeper. Therer are const layouts for each
- * skill level. In theory, the methods should compile down to constants or a Rect which can be constant.
- */
-#[derive(Debug, Copy, Clone)]
-pub struct Layout {
-    pub options: &'static Options,
-    constants: &'static LayoutConstants,
-}
+        // the window is wide enough for the grid, and for the header with the panels pulled in
+        let min_width =
+            2 * (inside + MIN_PANEL_INSET + DIGIT_PANEL_WIDTH + MIN_FACE_GAP) + FACE_WIDTH;
+        let width = std::cmp::max(grid_width + 2 * inside, min_width);
+        let header = Rect::new(
+            MARGIN as i32,
+            MARGIN as i32,
+            width - 2 * MARGIN,
+            HEADER_HEIGHT + 2 * BEVEL,
+        );
+        let grid_top = header.bottom() as u32 + HEADER_GAP + BEVEL;
+        let height = grid_top + grid_height + inside;
+        let grid = Rect::new(
+            ((width - grid_width) / 2) as i32,
+            grid_top as i32,
+            grid_width,
+            grid_height,
+        );
 
-pub const BEGINNER_LAYOUT: Layout = Layout {
-    options: &super::options::BEGINNER_OPTIONS,
-    constants: &BEGINNER_LAYOUT_CONSTANTS,
-};
-pub const INTERMEDIATE_LAYOUT: Layout = Layout {
-    options: &super::options::INTERMEDIATE_OPTIONS,
-    constants: &INTERMEDIATE_LAYOUT_CONSTANTS,
-};
-pub const EXPERT_LAYOUT: Layout = Layout {
-    options: &super::options::EXPERT_OPTIONS,
-    constants: &EXPERT_LAYOUT_CONSTANTS,
-};
+        let face_left = width / 2 - FACE_WIDTH / 2;
+        let face = Rect::new(
+            face_left as i32,
+            Layout::center(inside, HEADER_HEIGHT, FACE_HEIGHT) as i32,
+            FACE_WIDTH,
+            FACE_HEIGHT,
+        );
+        // pull the digit panels in from the sides when there isn't room between them and the face
+        let panel_offset = if face_left >= inside + PANEL_INSET + DIGIT_PANEL_WIDTH {
+            inside + PANEL_INSET
+        } else {
+            inside + MIN_PANEL_INSET
+        };
 
-impl Layout {
-    pub fn new() -> Self {
-        match Options::new().level() {
-            super::options::BEGINNER => BEGINNER_LAYOUT,
-            super::options::INTERMEDIATE => INTERMEDIATE_LAYOUT,
-            super::options::EXPERT => EXPERT_LAYOUT,
-            &_ => BEGINNER_LAYOUT,
+        Layout {
+            options: options,
+            width: width,
+            height: height,
+            header: header,
+            grid: grid,
+            face: face,
+            panel_offset: panel_offset,
         }
     }
 
@@ -91,72 +92,96 @@ impl Layout {
         TILE_SIDE
     }
 
+    /// The width of the raised and sunken edges of the frame.
+    pub fn bevel() -> u32 {
+        BEVEL
+    }
+
     pub fn height(&self) -> u32 {
-        self.constants.height
+        self.height
     }
 
     pub fn width(&self) -> u32 {
-        self.constants.width
+        self.width
+    }
+
+    /// The sunken panel holding the digit panels and the face, including its bevel.
+    pub fn header(&self) -> Rect {
+        self.header
     }
 
     pub fn grid(&self) -> Rect {
-        let width = self.options.columns * TILE_SIDE as i16;
-        let height = self.options.rows * TILE_SIDE as i16;
-        Rect::new(GRID_LEFT, GRID_TOP, width as u32, height as u32)
+        self.grid
     }
 
-    pub fn tile(&self, bounding_box: Rect, index: i16) -> Rect {
-        let (row, column) = self.options.row_column(index as u16);
-        let left = bounding_box.left() + (column * TILE_SIDE as i16) as i32;
-        let top = bounding_box.top() + (row * TILE_SIDE as i16) as i32;
-        Rect::new(left, top, TILE_SIDE, TILE_SIDE)
+    /// The sunken panel around the grid, including its bevel.
+    pub fn grid_frame(&self) -> Rect {
+        Rect::new(
+            self.grid.left() - BEVEL as i32,
+            self.grid.top() - BEVEL as i32,
+            self.grid.width() + 2 * BEVEL,
+            self.grid.height() + 2 * BEVEL,
+        )
     }
 
-    pub fn grid_tile(&self, index: i16) -> Rect {
-        let bounding_box = self.grid();
-        let (row, column) = self.options.row_column(index as u16);
-        let left = bounding_box.left() + (column * TILE_SIDE as i16) as i32;
-        let top = bounding_box.top() + (row * TILE_SIDE as i16) as i32;
+    pub fn tile(&self, bounding_box: Rect, index: u32) -> Rect {
+        let (row, column) = self.options.row_column(index);
+        let left = bounding_box.left() + column as i32 * TILE_SIDE as i32;
+        let top = bounding_box.top() + row as i32 * TILE_SIDE as i32;
         Rect::new(left, top, TILE_SIDE, TILE_SIDE)
     }
 
+    pub fn grid_tile(&self, index: u32) -> Rect {
+        self.tile(self.grid, index)
+    }
+
     pub fn timer_digit_panel(&self) -> Rect {
-        let left = self.width() - self.digit_panel_offset() - DIGIT_PANEL_WIDTH;
-        self.digit_panel(left, TIMER_TOP)
+        self.digit_panel(self.timer_left())
     }
 
     pub fn flag_digit_panel(&self) -> Rect {
-        let left = self.digit_panel_offset();
-        self.digit_panel(left, FLAG_TOP)
+        self.digit_panel(self.digit_panel_offset())
     }
 
     pub fn digit_panel_offset(&self) -> u32 {
-        self.constants.panel_offset
+        self.panel_offset
     }
 
     pub fn timer_digit(&self, position: u32) -> Rect {
-        let mut left = self.width() - self.digit_panel_offset() - DIGIT_PANEL_WIDTH;
-        left += DIGIT_PANEL_HORZ_MARGIN * (position + 1) + DIGIT_WIDTH * position;
-        let top = TIMER_TOP + DIGIT_PANEL_VERT_MARGIN;
-        Rect::new(left as i32, top as i32, DIGIT_WIDTH, DIGIT_HEIGHT)
+        self.digit(self.timer_left(), position)
     }
 
     pub fn flag_digit(&self, position: u32) -> Rect {
-        let mut left = self.digit_panel_offset();
-        left += DIGIT_PANEL_HORZ_MARGIN * (position + 1) + DIGIT_WIDTH * position;
-        let top = FLAG_TOP + DIGIT_PANEL_VERT_MARGIN;
-        Rect::new(left as i32, top as i32, DIGIT_WIDTH, DIGIT_HEIGHT)
+        self.digit(self.digit_panel_offset(), position)
     }
 
     pub fn face(&self) -> Rect {
-        let left = self.width() / 2 - FACE_WIDTH / 2;
-        Rect::new(left as i32, FACE_TOP as i32, FACE_WIDTH, FACE_HEIGHT)
+        self.face
+    }
+
+    // the top of something of size centered in a span, rounding down
+    fn center(start: u32, span: u32, size: u32) -> u32 {
+        start + (span - size + 1) / 2
+    }
+
+    fn timer_left(&self) -> u32 {
+        self.width() - self.digit_panel_offset() - DIGIT_PANEL_WIDTH
+    }
+
+    fn digit_panel_top(&self) -> u32 {
+        Layout::center(MARGIN + BEVEL, HEADER_HEIGHT, DIGIT_PANEL_HEIGHT)
+    }
+
+    fn digit(&self, panel_left: u32, position: u32) -> Rect {
+        let left = panel_left + DIGIT_PANEL_HORZ_MARGIN * (position + 1) + DIGIT_WIDTH * position;
+        let top = self.digit_panel_top() + DIGIT_PANEL_VERT_MARGIN;
+        Rect::new(left as i32, top as i32, DIGIT_WIDTH, DIGIT_HEIGHT)
     }
 
-    fn digit_panel(&self, left: u32, top: u32) -> Rect {
+    fn digit_panel(&self, left: u32) -> Rect {
         Rect::new(
             left as i32,
-            top as i32,
+            self.digit_panel_top() as i32,
             DIGIT_PANEL_WIDTH,
             DIGIT_PANEL_HEIGHT,
         )
@@ -165,15 +190,17 @@ impl Layout {
 
 #[cfg(test)]
 mod tests {
-    use super::Rect;
+    use super::{Layout, Rect};
+    use crate::config::{Options, BEGINNER_OPTIONS, EXPERT_OPTIONS, INTERMEDIATE_OPTIONS};
 
     #[test]
     fn test_attributes() {
-        let layout = super::BEGINNER_LAYOUT;
+        let layout = Layout::new(BEGINNER_OPTIONS);
         let bounding_box = layout.grid();
-        assert_eq!(layout.height(), super::BEGINNER_HEIGHT);
-        assert_eq!(layout.width(), super::BEGINNER_WIDTH);
+        assert_eq!(layout.height(), 276);
+        assert_eq!(layout.width(), 210);
         assert_eq!(layout.grid(), Rect::new(15, 81, 180, 180));
+        assert_eq!(layout.header(), Rect::new(12, 12, 186, 55));
         assert_eq!(
             layout.tile(bounding_box, 0),
             Rect::new(15, 81, super::TILE_SIDE, super::TILE_SIDE)
@@ -184,10 +211,7 @@ mod tests {
         );
         assert_eq!(layout.timer_digit_panel(), Rect::new(129, 21, 65, 37));
         assert_eq!(layout.flag_digit_panel(), Rect::new(16, 21, 65, 37));
-        assert_eq!(
-            layout.digit_panel_offset(),
-            super::BEGINNER_DIGIT_PANEL_OFFSET
-        );
+        assert_eq!(layout.digit_panel_offset(), 16);
         assert_eq!(layout.timer_digit(0), Rect::new(131, 23, 19, 33));
         assert_eq!(layout.timer_digit(1), Rect::new(152, 23, 19, 33));
         assert_eq!(layout.timer_digit(2), Rect::new(173, 23, 19, 33));
@@ -199,4 +223,62 @@ mod tests {
             Rect::new(84, 19, super::FACE_WIDTH, super::FACE_HEIGHT)
         );
     }
+
+    #[test]
+    fn test_sizes() {
+        let layout = Layout::new(INTERMEDIATE_OPTIONS);
+        assert_eq!((layout.width(), layout.height()), (350, 416));
+        assert_eq!(layout.grid(), Rect::new(15, 81, 320, 320));
+        assert_eq!(layout.digit_panel_offset(), 20);
+        assert_eq!(layout.timer_digit_panel(), Rect::new(265, 21, 65, 37));
+
+        let layout = Layout::new(EXPERT_OPTIONS);
+        assert_eq!((layout.width(), layout.height()), (630, 416));
+        assert_eq!(layout.grid(), Rect::new(15, 81, 600, 320));
+        assert_eq!(layout.face(), Rect::new(294, 19, 42, 42));
+    }
+
+    #[test]
+    fn test_custom() {
+        let layout = Layout::new(Options::custom(24, 40, 200).unwrap());
+        assert_eq!((layout.width(), layout.height()), (830, 576));
+        assert_eq!(layout.grid(), Rect::new(15, 81, 800, 480));
+        assert_eq!(layout.grid_tile(40), Rect::new(15, 101, 20, 20));
+        assert_eq!(layout.digit_panel_offset(), 20);
+
+        // narrow boards are centered in the narrowest window
+        let layout = Layout::new(Options::custom(3, 4, 2).unwrap());
+        assert_eq!((layout.width(), layout.height()), (210, 156));
+        assert_eq!(layout.grid(), Rect::new(65, 81, 80, 60));
+        assert_eq!(layout.digit_panel_offset(), 16);
+        assert_eq!(layout.face(), Layout::new(BEGINNER_OPTIONS).face());
+    }
+
+    #[test]
+    fn test_frame() {
+        let layout = Layout::new(BEGINNER_OPTIONS);
+        assert_eq!(layout.grid_frame(), Rect::new(12, 78, 186, 186));
+        // the header and the grid frame line up, and the margins match around the window
+        let layout = Layout::new(EXPERT_OPTIONS);
+        let (header, frame) = (layout.header(), layout.grid_frame());
+        assert_eq!(
+            (header.left(), header.right()),
+            (frame.left(), frame.right())
+        );
+        assert_eq!(header.top(), 12);
+        assert_eq!(layout.width() as i32 - frame.right(), 12);
+        assert_eq!(layout.height() as i32 - frame.bottom(), 12);
+        assert_eq!(frame.top() - header.bottom(), 11);
+
+        // the header is always centered on the face
+        for columns in 1..40 {
+            let layout = Layout::new(Options::custom(8, columns, 1).unwrap());
+            let (header, face) = (layout.header(), layout.face());
+            assert!(layout.width() >= layout.grid().width() + 30);
+            assert!(layout.flag_digit_panel().right() < face.left());
+            assert!(layout.timer_digit_panel().left() > face.right());
+            assert!(layout.flag_digit_panel().left() > header.left() + 3);
+            assert!(layout.timer_digit_panel().right() < header.right() - 3);
+        }
+    }
 }
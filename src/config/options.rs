@@ -1,16 +1,73 @@
+use snafu::{ensure, OptionExt};
 use std::env;
+use std::str::FromStr;
+
+use crate::sprites::Error;
+use crate::sprites::{
+    BoardTooBig, EmptyBoard, InvalidArgument, MineCount, UnknownFlag, UnknownLevel,
+};
 
 pub const BEGINNER: &str = "beginner";
 pub const INTERMEDIATE: &str = "intermediate";
 pub const EXPERT: &str = "expert";
+pub const CUSTOM: &str = "custom";
+
+const FIRST_CLICK: &str = "--first-click";
+const SEED: &str = "--seed";
+const QUESTION_MARKS: &str = "--question-marks";
+
+// the most tiles a custom board can have, a thousand or so on each side
+const MAX_TILES: i32 = 1024 * 1024;
+
+/**
+ * How the first reveal of a game is protected. The mines aren't placed until the first reveal, so that
+ * they can be placed around it.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FirstClick {
+    Any,     //< The first reveal can be a mine
+    Safe,    //< The first reveal is never a mine
+    Opening, //< The first reveal and its neighbors are never mines, so the first reveal opens an area
+    NoGuess, //< The first reveal opens an area, and the rest of the board can be solved from it without guessing
+}
+
+impl FirstClick {
+    /// The name used for it on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FirstClick::Any => "any",
+            FirstClick::Safe => "safe",
+            FirstClick::Opening => "opening",
+            FirstClick::NoGuess => "no-guess",
+        }
+    }
+}
+
+impl FromStr for FirstClick {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<FirstClick, ()> {
+        match name {
+            "any" => Ok(FirstClick::Any),
+            "safe" => Ok(FirstClick::Safe),
+            "opening" => Ok(FirstClick::Opening),
+            "no-guess" => Ok(FirstClick::NoGuess),
+            _ => Err(()),
+        }
+    }
+}
 
 /**
  * Minesweeper configuration options.
  *
- * minesweeper level [beginner|intermediate|expert]
+ * minesweeper [beginner|intermediate|expert|custom rows columns mines]
+ *             [--first-click any|safe|opening|no-guess]
+ *             [--seed number]
+ *             [--question-marks on|off]
  *
- * Its a bit messy, but the goal is to have everything compile down to constants. This should improve the layout
- * engine, which also should compile down to constants.
+ * The skill levels are constants, and the Layout is computed from whichever options are chosen. Question marks
+ * are a preference rather than part of the level, with them on a right click goes from a flag to a question mark
+ * before the tile is hidden again.
  */
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -18,7 +75,10 @@ pub struct Options {
     level: &'static str,
     pub rows: i16,
     pub columns: i16,
-    mines: i16,
+    mines: i32,
+    first_click: FirstClick,
+    seed: Option<u64>,
+    question_marks: bool,
 }
 
 pub const BEGINNER_OPTIONS: Options = Options {
@@ -26,6 +86,9 @@ pub const BEGINNER_OPTIONS: Options = Options {
     rows: 9,
     columns: 9,
     mines: 10,
+    first_click: FirstClick::Safe,
+    seed: None,
+    question_marks: true,
 };
 
 pub const INTERMEDIATE_OPTIONS: Options = Options {
@@ -33,6 +96,9 @@ pub const INTERMEDIATE_OPTIONS: Options = Options {
     rows: 16,
     columns: 16,
     mines: 40,
+    first_click: FirstClick::Safe,
+    seed: None,
+    question_marks: true,
 };
 
 pub const EXPERT_OPTIONS: Options = Options {
@@ -40,51 +106,152 @@ pub const EXPERT_OPTIONS: Options = Options {
     rows: 16,
     columns: 30,
     mines: 99,
+    first_click: FirstClick::Safe,
+    seed: None,
+    question_marks: true,
 };
 
 impl Options {
-    pub fn new() -> Options {
+    pub fn new() -> Result<Options, Error> {
         let args: Vec<_> = env::args().collect();
         Options::new_with_args(args)
     }
 
-    pub fn new_with_args(args: Vec<String>) -> Options {
-        match args.get(1).as_ref() {
+    pub fn new_with_args(args: Vec<String>) -> Result<Options, Error> {
+        Options::new_with_flags(args, &[])
+    }
+
+    /// Create options from the command line, which can have the other flags too, each with a value. Any other
+    /// flag is an error, rather than being ignored.
+    pub fn new_with_flags(args: Vec<String>, other_flags: &[&str]) -> Result<Options, Error> {
+        let mut options = match args.get(1).map(|s| s.as_ref()) {
             None => BEGINNER_OPTIONS,
-            Some(skill_level) => match skill_level.as_ref() {
-                BEGINNER => BEGINNER_OPTIONS,
-                INTERMEDIATE => INTERMEDIATE_OPTIONS,
-                EXPERT => EXPERT_OPTIONS,
-                _ => BEGINNER_OPTIONS,
-            },
+            Some(flag) if flag.starts_with("--") => BEGINNER_OPTIONS,
+            Some(BEGINNER) => BEGINNER_OPTIONS,
+            Some(INTERMEDIATE) => INTERMEDIATE_OPTIONS,
+            Some(EXPERT) => EXPERT_OPTIONS,
+            Some(CUSTOM) => Options::custom(
+                parse_arg("rows", args.get(2))?,
+                parse_arg("columns", args.get(3))?,
+                parse_arg("mines", args.get(4))?,
+            )?,
+            Some(level) => return UnknownLevel { level }.fail(),
+        };
+        let mut iter = args.iter().skip(1);
+        while let Some(arg) = iter.next() {
+            if arg == FIRST_CLICK {
+                options.first_click = parse_arg(FIRST_CLICK, iter.next())?;
+            } else if arg == SEED {
+                options.seed = Some(parse_arg(SEED, iter.next())?);
+            } else if arg == QUESTION_MARKS {
+                options.question_marks = match iter.next().map(String::as_str) {
+                    Some("on") => true,
+                    Some("off") => false,
+                    value => {
+                        let value = value.unwrap_or_default();
+                        return InvalidArgument {
+                            name: QUESTION_MARKS,
+                            value,
+                        }
+                        .fail();
+                    }
+                };
+            } else if other_flags.contains(&arg.as_str()) {
+                iter.next();
+            } else if arg.starts_with("--") {
+                return UnknownFlag { flag: arg }.fail();
+            }
+        }
+        Ok(options)
+    }
+
+    /// Create options for a custom level, checking that the board is playable.
+    pub fn custom(rows: i16, columns: i16, mines: i32) -> Result<Options, Error> {
+        ensure!(rows > 0 && columns > 0, EmptyBoard { rows, columns });
+        let limit = MAX_TILES;
+        ensure!(
+            rows as i32 * columns as i32 <= limit,
+            BoardTooBig {
+                rows,
+                columns,
+                limit
+            }
+        );
+        let tiles = rows as i32 * columns as i32;
+        ensure!(mines >= 0 && mines < tiles, MineCount { mines, tiles });
+        Ok(Options {
+            level: CUSTOM,
+            rows: rows,
+            columns: columns,
+            mines: mines,
+            ..BEGINNER_OPTIONS
+        })
+    }
+
+    /// Get a copy of these options with a different first click protection.
+    pub fn with_first_click(&self, first_click: FirstClick) -> Options {
+        Options {
+            first_click: first_click,
+            ..*self
+        }
+    }
+
+    /// Get a copy of these options with question marks turned on or off.
+    pub fn with_question_marks(&self, question_marks: bool) -> Options {
+        Options {
+            question_marks: question_marks,
+            ..*self
         }
     }
 
+    /// Whether a right click on a flag makes it a question mark, rather than hiding it again.
+    pub fn question_marks(&self) -> bool {
+        self.question_marks
+    }
+
     pub fn level(&self) -> &str {
         self.level
     }
 
-    pub fn tiles(&self) -> i16 {
-        self.rows * self.columns
+    pub fn tiles(&self) -> i32 {
+        self.rows as i32 * self.columns as i32
     }
 
-    pub fn blanks(&self) -> i16 {
+    pub fn blanks(&self) -> i32 {
         self.tiles() - self.mines
     }
 
-    pub fn mines(&self) -> i16 {
+    pub fn mines(&self) -> i32 {
         self.mines
     }
 
-    pub fn row_column(&self, index: u16) -> (i16, i16) {
-        (index as i16 / self.columns, index as i16 % self.columns)
+    pub fn first_click(&self) -> FirstClick {
+        self.first_click
+    }
+
+    /// Get a copy of these options which generates the board from seed.
+    pub fn with_seed(&self, seed: u64) -> Options {
+        Options {
+            seed: Some(seed),
+            ..*self
+        }
     }
 
-    pub fn index(&self, row: i16, column: i16) -> u16 {
-        (row * self.columns + column) as u16
+    /// The seed for the first board, when there isn't one the seed is random.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
     }
 
-    pub fn for_each_neighbor<F>(&self, index: u16, mut closure: F)
+    pub fn row_column(&self, index: u32) -> (i16, i16) {
+        let columns = self.columns as u32;
+        ((index / columns) as i16, (index % columns) as i16)
+    }
+
+    pub fn index(&self, row: i16, column: i16) -> u32 {
+        row as u32 * self.columns as u32 + column as u32
+    }
+
+    pub fn for_each_neighbor<F>(&self, index: u32, mut closure: F)
     where
         F: FnMut(i16, i16),
     {
@@ -101,9 +268,15 @@ impl Options {
     }
 }
 
+fn parse_arg<T: FromStr>(name: &str, value: Option<&String>) -> Result<T, Error> {
+    let value = value.map(String::as_str).unwrap_or_default();
+    value.parse().ok().context(InvalidArgument { name, value })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Options;
+    use super::{FirstClick, Options};
+    use crate::sprites::Error;
 
     #[test]
     fn test_construction() {
@@ -113,7 +286,10 @@ mod tests {
                 level: "beginner",
                 rows: 9,
                 columns: 9,
-                mines: 10
+                mines: 10,
+                first_click: FirstClick::Safe,
+                seed: None,
+                question_marks: true,
             }
         );
         assert_eq!(
@@ -122,7 +298,10 @@ mod tests {
                 level: "intermediate",
                 rows: 16,
                 columns: 16,
-                mines: 40
+                mines: 40,
+                first_click: FirstClick::Safe,
+                seed: None,
+                question_marks: true,
             }
         );
         assert_eq!(
@@ -131,38 +310,128 @@ mod tests {
                 level: "expert",
                 rows: 16,
                 columns: 30,
-                mines: 99
+                mines: 99,
+                first_click: FirstClick::Safe,
+                seed: None,
+                question_marks: true,
             }
         );
     }
 
+    fn parse(args: &[&str]) -> Result<Options, Error> {
+        let mut args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        args.insert(0, "minesweeper".to_string());
+        Options::new_with_args(args)
+    }
+
     #[test]
     fn test_command_line() {
-        let mut args = vec!["minesweeper".to_string(), "beginner".to_string()];
-        assert_eq!(Options::new_with_args(args), super::BEGINNER_OPTIONS);
+        assert_eq!(parse(&["beginner"]).unwrap(), super::BEGINNER_OPTIONS);
+        assert_eq!(
+            parse(&["intermediate"]).unwrap(),
+            super::INTERMEDIATE_OPTIONS
+        );
+        assert_eq!(parse(&["expert"]).unwrap(), super::EXPERT_OPTIONS);
+        assert_eq!(parse(&[]).unwrap(), super::BEGINNER_OPTIONS);
+        assert_eq!(
+            parse(&["wrong"]).unwrap_err().to_string(),
+            "unknown level wrong, it should be beginner, intermediate, expert or custom"
+        );
 
-        args = vec!["minesweeper".to_string(), "intermediate".to_string()];
-        assert_eq!(Options::new_with_args(args), super::INTERMEDIATE_OPTIONS);
+        let options = parse(&["expert", "--first-click", "opening"]).unwrap();
+        assert_eq!(options.level(), "expert");
+        assert_eq!(options.first_click(), FirstClick::Opening);
+        assert_eq!(
+            parse(&["--first-click", "any"]).unwrap(),
+            super::BEGINNER_OPTIONS.with_first_click(FirstClick::Any)
+        );
+        assert_eq!(
+            parse(&["--first-click", "no-guess"]).unwrap().first_click(),
+            FirstClick::NoGuess
+        );
+        assert!(parse(&["--first-click", "sometimes"]).is_err());
 
-        args = vec!["minesweeper".to_string(), "expert".to_string()];
-        assert_eq!(Options::new_with_args(args), super::EXPERT_OPTIONS);
+        assert_eq!(
+            parse(&["intermediate", "--seed", "1234"]).unwrap(),
+            super::INTERMEDIATE_OPTIONS.with_seed(1234)
+        );
+        assert!(parse(&["--seed"]).is_err());
 
-        args = vec!["minesweeper".to_string()];
-        assert_eq!(Options::new_with_args(args), super::BEGINNER_OPTIONS);
+        assert!(parse(&[]).unwrap().question_marks());
+        assert_eq!(
+            parse(&["expert", "--question-marks", "off"]).unwrap(),
+            super::EXPERT_OPTIONS.with_question_marks(false)
+        );
+        assert_eq!(
+            parse(&["--question-marks", "maybe"])
+                .unwrap_err()
+                .to_string(),
+            "\"maybe\" is not a valid value for --question-marks"
+        );
 
-        args = vec!["minesweeper".to_string(), "wrong".to_string()];
-        assert_eq!(Options::new_with_args(args), super::BEGINNER_OPTIONS);
+        assert_eq!(
+            parse(&["custom", "20", "40", "150", "--seed", "1"]).unwrap(),
+            Options::custom(20, 40, 150).unwrap().with_seed(1)
+        );
+        assert_eq!(
+            parse(&["custom", "20", "forty", "150"])
+                .unwrap_err()
+                .to_string(),
+            "\"forty\" is not a valid value for columns"
+        );
+        assert!(parse(&["custom", "20", "40"]).is_err());
+
+        // a misspelt flag isn't ignored, but the flags for the rest of the program are let through
+        assert_eq!(
+            parse(&["expert", "--sead", "5"]).unwrap_err().to_string(),
+            "unknown flag --sead"
+        );
+        let args = ["minesweeper", "--record", "--seed", "--seed", "7"];
+        let args = args.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            Options::new_with_flags(args, &["--record"]).unwrap(),
+            super::BEGINNER_OPTIONS.with_seed(7)
+        );
+    }
+
+    #[test]
+    fn test_custom() {
+        let options = Options::custom(20, 40, 150).unwrap();
+        assert_eq!(options.level(), "custom");
+        assert_eq!(options.rows, 20);
+        assert_eq!(options.columns, 40);
+        assert_eq!(options.mines(), 150);
+        assert_eq!(options.first_click(), FirstClick::Safe);
+
+        assert!(Options::custom(1, 1, 0).is_ok());
+        assert_eq!(
+            Options::custom(0, 10, 5).unwrap_err().to_string(),
+            "a board needs at least one row and one column, not 0x10"
+        );
+        assert!(Options::custom(10, -1, 5).is_err());
+        // boards with hundreds of thousands of tiles can be played
+        let options = Options::custom(500, 400, 30000).unwrap();
+        assert_eq!(options.tiles(), 200000);
+        assert_eq!(options.row_column(199999), (499, 399));
+        assert_eq!(options.index(499, 399), 199999);
+        assert_eq!(
+            Options::custom(2000, 2000, 5).unwrap_err().to_string(),
+            "a 2000x2000 board is too big, it can have at most 1048576 tiles"
+        );
+        assert_eq!(
+            Options::custom(9, 9, 81).unwrap_err().to_string(),
+            "a board with 81 tiles can have 0 to 80 mines, not 81"
+        );
+        assert!(Options::custom(9, 9, -1).is_err());
     }
 
     #[test]
     fn test_attributes() {
         fn run(options: &Options) {
-            assert_eq!(options.rows * options.columns, options.tiles());
+            let tiles = options.rows as i32 * options.columns as i32;
+            assert_eq!(tiles, options.tiles());
             assert_eq!(options.mines, options.mines());
-            assert_eq!(
-                options.rows * options.columns - options.mines,
-                options.blanks()
-            );
+            assert_eq!(tiles - options.mines, options.blanks());
         }
 
         assert_eq!(super::BEGINNER_OPTIONS.level(), "beginner");
@@ -175,8 +444,8 @@ mod tests {
 
     #[test]
     fn test_for_each_neighbor() {
-        fn run(options: &Options, index: u16) -> Vec<u16> {
-            let mut visit: Vec<u16> = Vec::new();
+        fn run(options: &Options, index: u32) -> Vec<u32> {
+            let mut visit: Vec<u32> = Vec::new();
             let closure = |row, col| visit.push(options.index(row, col));
             options.for_each_neighbor(index, closure);
             visit
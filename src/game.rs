@@ -1,2 +1,11 @@
 mod game;
 pub use game::Game;
+mod replay;
+pub use replay::{Replay, ReplayPlayer};
+mod result;
+pub use result::GameResult;
+mod save;
+pub use save::SavedGame;
+
+#[cfg(test)]
+mod golden;
@@ -1,17 +1,49 @@
+use std::any::TypeId;
+use std::cell::{Ref, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
 use crate::config::Layout;
-use crate::sprites::Error;
+use crate::game::{GameResult, Replay, SavedGame};
+use crate::model::Board;
 use crate::sprites::{Background, Button, FlagCounter, Grid, Sprite, TimeCounter};
-use crate::sprites::{ChannelMessage, ChannelWiring, Exchange, MessageExchange};
-use crate::sprites::{Renderer, RendererContext};
+use crate::sprites::{ChannelMessage, ChannelWiring, Exchange, MessageExchange, Schedule, Sender};
+use crate::sprites::{Clock, InstantClock};
+use crate::sprites::{Error, InvalidBoard, ResumeData};
+use crate::sprites::Trace;
 
+/**
+ * Game owns the board and the sprites, and hands their messages out. Every message sent through the wiring is
+ * scheduled, and each pull hands the oldest ones to whoever they were sent to, the Game itself or one of the
+ * sprites, which takes all of the messages sent to it in a row as one batch. So the messages are handled
+ * strictly in the order they were sent, across all of the sprites: a click reaches
+ * each sprite in the order the Game was wired to them, and anything sent while handling it is handled after
+ * the click has reached all of them, in the order it was sent.
+ *
+ * The front ends send a message with send, which runs the game until nothing is left to handle.
+ */
 pub struct Game {
-    sprites: Vec<Box<dyn Sprite>>,
-    sender: std::sync::mpsc::Sender<ChannelMessage>,
+    board: Rc<RefCell<Board>>,
+    sprites: Vec<(TypeId, Box<dyn Sprite>)>,
+    sender: Sender<ChannelMessage>,
     exchange: Exchange,
+    schedule: Schedule,
+    wiring: ChannelWiring,
+    clock: Rc<dyn Clock>,
+    replay: Option<Replay>,
+    board_start: Duration,
+    // the time the board has been played for is kept by the counter which shows it
+    time_counter: TimeCounter,
 }
 
 impl Game {
+    /// Create a game which keeps time with std::time::Instant.
     pub fn new(layout: Layout) -> Game {
+        Game::with_clock(layout, Rc::new(InstantClock::new()))
+    }
+
+    /// Create a game which keeps time with the clock, for platforms without std::time::Instant and for tests.
+    pub fn with_clock(layout: Layout, clock: Rc<dyn Clock>) -> Game {
         // get all the channel wiring setup
         let mut channels = ChannelWiring::default();
         channels.wire::<Grid, Button>();
@@ -21,8 +53,6 @@ impl Game {
         channels.wire::<Button, FlagCounter>();
         channels.wire::<Button, Grid>();
 
-        channels.wire::<FlagCounter, Grid>();
-
         channels.wire::<Game, Background>();
         channels.wire::<Game, TimeCounter>();
         channels.wire::<Game, FlagCounter>();
@@ -31,86 +61,259 @@ impl Game {
         struct Minesweeper;
         channels.wire::<Minesweeper, Game>();
 
-        let mut sprites: Vec<Box<dyn Sprite>> = Vec::new();
+        // the board is the model, the grid plays moves on it and everyone else can look at it
+        let board = Rc::new(RefCell::new(Board::new(layout.options)));
 
-        // create the underlying objects, and own via trait
-        sprites.push(Box::new(Background::new(&mut channels)));
-        sprites.push(Box::new(TimeCounter::new(&mut channels)));
-        sprites.push(Box::new(FlagCounter::new(layout, &mut channels)));
-        sprites.push(Box::new(Button::new(layout, &mut channels)));
-        sprites.push(Box::new(Grid::new(layout, &mut channels)));
+        let mut sprites: Vec<(TypeId, Box<dyn Sprite>)> = Vec::new();
+        let time_counter = TimeCounter::new(clock.clone(), &mut channels);
+
+        // create the underlying objects, and own via trait with the type their messages are scheduled for
+        sprites.push((
+            TypeId::of::<Background>(),
+            Box::new(Background::new(&mut channels)),
+        ));
+        sprites.push((
+            TypeId::of::<FlagCounter>(),
+            Box::new(FlagCounter::new(layout, &mut channels)),
+        ));
+        sprites.push((
+            TypeId::of::<Button>(),
+            Box::new(Button::new(layout, &mut channels)),
+        ));
+        sprites.push((
+            TypeId::of::<Grid>(),
+            Box::new(Grid::new(layout, board.clone(), &mut channels)),
+        ));
 
         // finally create the game object
         let (senders, _) = channels.channels::<Minesweeper>();
         let sender = senders.unwrap().pop().unwrap();
+        let exchange = Exchange::new_from_wiring::<Game>(&mut channels);
+
+        // a mistake in the wiring would otherwise only show up as messages which never arrive, the wiring is
+        // fixed so it's caught by the tests, and a release build doesn't stop for it
+        let validated = channels.validate();
+        debug_assert!(validated.is_ok(), "{}", validated.unwrap_err());
         Game {
+            board: board,
             sprites: sprites,
             sender: sender,
-            exchange: Exchange::new_from_wiring::<Game>(&mut channels),
+            exchange: exchange,
+            schedule: channels.schedule(),
+            wiring: channels,
+            clock: clock,
+            replay: None,
+            board_start: Duration::default(),
+            time_counter: time_counter,
         }
     }
 
-    pub fn get_sender(&self) -> std::sync::mpsc::Sender<ChannelMessage> {
+    pub fn get_sender(&self) -> Sender<ChannelMessage> {
         self.sender.clone()
     }
-}
 
-impl MessageExchange for Game {
-    fn pull(&mut self) -> u32 {
-        let mut count = self.exchange.pull();
-        for message in self.exchange.get_messages().iter() {
-            match message {
-                ChannelMessage::Render(_) => self.exchange.push_message(message.clone()),
-                ChannelMessage::MouseEvent(data) => self.exchange.push_message(message.clone()),
-                _ => (),
+    /// How the game and the sprites are wired together, e.g. to draw it with to_dot.
+    pub fn wiring(&self) -> &ChannelWiring {
+        &self.wiring
+    }
+
+    /// The trace of the messages between the game and the sprites, it's off until it's turned on.
+    pub fn trace(&self) -> &Trace {
+        self.wiring.trace()
+    }
+
+    /// Send the message to the game and handle it, and everything it causes, returning how many messages were
+    /// handled.
+    pub fn send(&mut self, message: ChannelMessage) -> u32 {
+        // the game holds its own receiver, so the message can always be sent
+        self.sender.send(message).unwrap();
+        self.run_until_quiescent()
+    }
+
+    /// Handle the messages which are waiting, in the order they were sent, until there are none left. Returns
+    /// how many were handled.
+    pub fn run_until_quiescent(&mut self) -> u32 {
+        let mut count = 0;
+        loop {
+            match self.pull() {
+                0 => return count,
+                handled => count += handled,
             }
         }
+    }
+
+    /// The seed of the board being played, a game started with it in the Options will have the same mines.
+    pub fn seed(&self) -> u64 {
+        self.board.borrow().seed()
+    }
+
+    /// The board being played. Moves should be made through the sender, so that the sprites stay in step.
+    pub fn board(&self) -> Ref<Board> {
+        self.board.borrow()
+    }
+
+    /// Start the board again, with the mines placed from the seed on the first reveal. It's for a board which
+    /// hasn't been played yet, as the sprites aren't told about it.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.board.borrow_mut().reset_with_seed(seed);
+    }
+
+    /// How long the board has been played for, the time stops when the game is over.
+    pub fn elapsed(&self) -> Duration {
+        self.time_counter.elapsed()
+    }
 
-        for sprite in self.sprites.iter_mut() {
-            count += sprite.pull();
+    /// How the board being played has gone so far, with the penalty for the hints.
+    pub fn result(&self) -> GameResult {
+        let board = self.board.borrow();
+        GameResult {
+            state: board.state(),
+            elapsed: self.elapsed(),
+            hints: board.hints(),
         }
-        count
     }
-}
 
+    /// Save the board being played, with the time it has been played for.
+    pub fn save(&self) -> SavedGame {
+        SavedGame::new(&self.board.borrow(), self.elapsed())
+    }
 
+    /// Carry on with a saved game, which has to be for a board with the same size and number of mines.
+    pub fn restore(&mut self, saved: &SavedGame) -> Result<(), Error> {
+        self.resume(saved.board()?, saved.elapsed())
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::{Layout, BEGINNER_LAYOUT};
-    use crate::media_layer::Texture;
-    use crate::sprites::Rect;
-    use std::rc::Rc;
+    /// Play the board, e.g. one written by hand, rather than one placed from a seed. It has to have the same
+    /// size and number of mines.
+    pub fn set_board(&mut self, board: Board) -> Result<(), Error> {
+        self.resume(board, Duration::default())
+    }
+
+    // swap the board in, and let the sprites know how it was left
+    fn resume(&mut self, board: Board, elapsed: Duration) -> Result<(), Error> {
+        let (from, to) = (*board.options(), *self.board.borrow().options());
+        snafu::ensure!(
+            (from.rows, from.columns, from.mines()) == (to.rows, to.columns, to.mines()),
+            InvalidBoard {
+                reason: format!(
+                    "it's {}x{} with {} mines, the game is {}x{} with {}",
+                    from.rows,
+                    from.columns,
+                    from.mines(),
+                    to.rows,
+                    to.columns,
+                    to.mines()
+                )
+            }
+        );
+        // the board is played with the player's preference, whatever it was saved with
+        let board = board.with_question_marks(to.question_marks());
+        let data = ResumeData {
+            state: board.state(),
+            flags_remaining: board.flags_remaining(),
+            elapsed: elapsed,
+        };
+        *self.board.borrow_mut() = board;
+        self.exchange.push_message(ChannelMessage::Resume(data));
+        self.run_until_quiescent();
+        Ok(())
+    }
+
+    /// Start recording the mouse events and hints, from the board being played.
+    pub fn record(&mut self) {
+        let mut replay = Replay::new(*self.board.borrow().options());
+        replay.start_board(self.seed());
+        self.replay = Some(replay);
+        self.board_start = self.clock.now();
+    }
 
-    struct TestRendering {}
-    impl RendererContext for TestRendering {
-        fn render_image(
-            &self,
-            texture: &Texture,
-            src: Option<Rect>,
-            dst: Rect,
-        ) -> Result<(), String> {
-            Ok(())
+    /// The recording, if it was started.
+    pub fn replay(&self) -> Option<&Replay> {
+        self.replay.as_ref()
+    }
+
+    // hand the oldest run of messages to the game or the sprite they were sent to
+    fn deliver(&mut self, receiver: TypeId) -> u32 {
+        if receiver == TypeId::of::<Game>() {
+            return self.forward();
         }
-        fn layout(&self) -> &Layout {
-            &BEGINNER_LAYOUT
+        if receiver == TypeId::of::<TimeCounter>() {
+            return self.time_counter.pull();
         }
-        fn load(&self, name: &str) -> Result<Rc<Texture>, String> {
-            Err("image not found".to_string())
+        match self.sprites.iter_mut().find(|(id, _)| *id == receiver) {
+            Some((_, sprite)) => sprite.pull(),
+            None => 0,
         }
-        fn load_digit(&self, value: u64) -> Result<Rc<Texture>, String> {
-            Err("image not found".to_string())
+    }
+
+    // the messages for the game are passed on to the sprites, and the mouse events and hints are recorded
+    fn forward(&mut self) -> u32 {
+        let count = self.exchange.pull();
+        for message in self.exchange.get_messages().iter() {
+            match message {
+                ChannelMessage::Render(_) | ChannelMessage::ToggleHeatmap => {
+                    self.exchange.push_message(message.clone())
+                }
+                ChannelMessage::Hint => {
+                    if let Some(replay) = self.replay.as_mut() {
+                        replay.push_hint(self.clock.now() - self.board_start);
+                    }
+                    self.exchange.push_message(message.clone())
+                }
+                ChannelMessage::MouseEvent(data) => {
+                    if let Some(replay) = self.replay.as_mut() {
+                        replay.push(self.clock.now() - self.board_start, *data);
+                    }
+                    self.exchange.push_message(message.clone())
+                }
+                _ => (),
+            }
         }
-        fn load_tile(&self, value: u64) -> Result<Rc<Texture>, String> {
-            Err("image not found".to_string())
+        count
+    }
+}
+
+impl MessageExchange for Game {
+    /// Handle the oldest run of messages for one receiver waiting anywhere in the game, returning how many
+    /// there were, or 0 when there are none.
+    fn pull(&mut self) -> u32 {
+        // messages which have already been taken, by pulling a sprite directly, are skipped
+        let schedule = self.schedule.clone();
+        let handled = loop {
+            match schedule.next() {
+                Some((receiver, last)) => match schedule.deliver(last, || self.deliver(receiver)) {
+                    0 => (),
+                    handled => break handled,
+                },
+                None => return 0,
+            }
+        };
+
+        // a new board was started, record its seed
+        let seed = self.seed();
+        if let Some(replay) = self.replay.as_mut() {
+            if replay.seed() != Some(seed) {
+                replay.start_board(seed);
+                self.board_start = self.clock.now();
+            }
         }
-        fn end_rendering(&self) {}
+        handled
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{FirstClick, Layout, Options, BEGINNER_OPTIONS, EXPERT_OPTIONS};
+    use crate::media_layer::HeadlessContext;
+    use crate::model::{CellView, GameState};
+    use crate::sprites::{MouseButton, RendererContext};
+    use crate::testing::{click, click_event, click_on, TestClock};
+    use std::rc::Rc;
 
     #[test]
     fn test_construction() {
-        let layout = BEGINNER_LAYOUT;
+        let layout = Layout::new(BEGINNER_OPTIONS);
         let mut game = Game::new(layout);
         let sender = game.get_sender();
         sender.send(ChannelMessage::TestMessage);
@@ -119,14 +322,207 @@ mod tests {
 
     #[test]
     fn test_render() {
-        let context = TestRendering {};
+        let context = HeadlessContext::new(Layout::new(BEGINNER_OPTIONS));
         let rendering_context = Rc::new(Box::new(context) as Box<dyn RendererContext>);
 
-        let layout = BEGINNER_LAYOUT;
+        let layout = Layout::new(BEGINNER_OPTIONS);
         let mut game = Game::new(layout);
         let sender = game.get_sender();
         let message = ChannelMessage::Render(Rc::clone(&rendering_context));
         sender.send(message);
         game.pull();
     }
+
+    #[test]
+    fn test_save() {
+        let clock = Rc::new(TestClock::new(Duration::from_secs(50)));
+        let layout = Layout::new(BEGINNER_OPTIONS);
+        let mut game = Game::with_clock(layout, clock.clone());
+        click(&mut game, 40, MouseButton::Left);
+        clock.set(Duration::from_secs(62));
+        let saved = game.save();
+        assert_eq!(saved.elapsed(), Duration::from_secs(12));
+
+        // the time carries on from where it was saved
+        let mut restored = Game::with_clock(layout, clock.clone());
+        restored.restore(&saved).unwrap();
+        assert_eq!(restored.board().state(), game.board().state());
+        assert_eq!(restored.board().mines(), game.board().mines());
+        clock.set(Duration::from_secs(65));
+        assert_eq!(restored.elapsed(), Duration::from_secs(15));
+        assert_eq!(restored.save().to_string(), game.save().to_string());
+
+        // a game with question marks turned off carries on without them
+        let options = saved.options().with_question_marks(false);
+        let mut unmarked = Game::with_clock(Layout::new(options), clock.clone());
+        unmarked.restore(&saved).unwrap();
+        assert!(!unmarked.board().options().question_marks());
+        let hidden = (0..81).find(|index| unmarked.board().cell_at(*index).is_hidden());
+        let hidden = hidden.unwrap();
+        click(&mut unmarked, hidden, MouseButton::Right);
+        click(&mut unmarked, hidden, MouseButton::Right);
+        assert_eq!(unmarked.board().cell_at(hidden), CellView::Hidden);
+
+        // it has to be the same size of board
+        let mut expert = Game::with_clock(Layout::new(EXPERT_OPTIONS), clock);
+        assert!(expert.restore(&saved).is_err());
+    }
+
+    #[test]
+    fn test_hint() {
+        // before the first reveal nothing is certain, so the hint reveals a tile
+        let layout = Layout::new(BEGINNER_OPTIONS);
+        let mut game = Game::new(layout);
+        game.send(ChannelMessage::Hint);
+        assert_eq!(game.board().state(), GameState::Playing);
+        assert_eq!(game.result().hints, 1);
+
+        // and there are no hints once the game is over
+        let board: Board = "*X\n..\n".parse().unwrap();
+        let mut game = Game::new(Layout::new(*board.options()));
+        game.set_board(board).unwrap();
+        game.send(ChannelMessage::Hint);
+        assert_eq!(game.result().state, GameState::Lose);
+        assert_eq!(game.result().hints, 0);
+    }
+
+    #[test]
+    fn test_chord() {
+        // a middle click on a number with its mines flagged reveals the rest of its neighbors
+        let board: Board = "*..\n...\n...\n".parse().unwrap();
+        let options = *board.options();
+        let mut game = Game::new(Layout::new(options));
+        game.set_board(board).unwrap();
+        click(&mut game, 4, MouseButton::Left);
+        click(&mut game, 4, MouseButton::Middle);
+        assert_eq!(game.board().to_string(), "*..\n.1.\n...\n");
+        click(&mut game, 0, MouseButton::Right);
+        click(&mut game, 4, MouseButton::Middle);
+        assert_eq!(game.board().to_string(), "F10\n110\n000\n");
+        assert_eq!(game.board().state(), GameState::Win);
+    }
+
+    #[test]
+    fn test_set_board() {
+        let board: Board = "*..\n...\n..*\n".parse().unwrap();
+        let options = *board.options();
+        let layout = Layout::new(options);
+        let mut game = Game::new(layout);
+        game.set_board(board).unwrap();
+
+        // the grid plays the board it was given
+        click(&mut game, options.index(0, 2), MouseButton::Left);
+        assert_eq!(game.board().to_string(), "*10\n.21\n..*\n");
+        assert_eq!(game.board().state(), GameState::Playing);
+
+        let board: Board = "*.\n".parse().unwrap();
+        assert!(game.set_board(board).is_err());
+    }
+
+    #[test]
+    fn test_send() {
+        // a render is handed to the game, which passes it on to each of the five sprites
+        let layout = Layout::new(BEGINNER_OPTIONS);
+        let mut game = Game::new(layout);
+        let context = HeadlessContext::new(layout);
+        let rendering_context = Rc::new(Box::new(context) as Box<dyn RendererContext>);
+        assert_eq!(game.send(ChannelMessage::Render(rendering_context)), 6);
+        assert_eq!(game.pull(), 0);
+
+        // what a message causes is handled before send returns, the face starts a lost game again
+        let board: Board = "*X\n..\n".parse().unwrap();
+        let layout = Layout::new(*board.options());
+        let mut game = Game::new(layout);
+        game.set_board(board).unwrap();
+        click_on(&mut game, layout.face(), MouseButton::Left);
+        assert_eq!(game.board().state(), GameState::Init);
+    }
+
+    #[test]
+    fn test_order() {
+        // messages sent together are handled in the order they were sent, the flag goes on the revealed
+        // tile's neighbor before the second click tries to chord it
+        let board: Board = "*..\n...\n...\n".parse().unwrap();
+        let layout = Layout::new(*board.options());
+        let mut game = Game::new(layout);
+        game.set_board(board).unwrap();
+        let sender = game.get_sender();
+        for (index, mouse_btn) in [
+            (4, MouseButton::Left),
+            (0, MouseButton::Right),
+            (4, MouseButton::Middle),
+        ]
+        .iter()
+        {
+            let event = click_event(layout.grid_tile(*index), *mouse_btn);
+            sender.send(ChannelMessage::MouseEvent(event)).unwrap();
+        }
+        game.run_until_quiescent();
+        assert_eq!(game.board().to_string(), "F10\n110\n000\n");
+    }
+
+    #[test]
+    fn test_large_board() {
+        // a board of a hundred thousand tiles goes through as many messages as a small one, in as many pulls
+        let handled = |options: Options| {
+            let options = options.with_first_click(FirstClick::Opening).with_seed(1);
+            let layout = Layout::new(options);
+            let mut game = Game::new(layout);
+            let center = options.index(options.rows / 2, options.columns / 2);
+            let context = HeadlessContext::new(layout);
+            let rendering_context = Rc::new(Box::new(context) as Box<dyn RendererContext>);
+            let sender = game.get_sender();
+            let event = click_event(layout.grid_tile(center), MouseButton::Left);
+            sender.send(ChannelMessage::MouseEvent(event)).unwrap();
+            sender
+                .send(ChannelMessage::Render(Rc::clone(&rendering_context)))
+                .unwrap();
+            let (mut pulls, mut messages) = (0, 0);
+            loop {
+                match game.pull() {
+                    0 => break,
+                    handled => messages += handled,
+                }
+                pulls += 1;
+            }
+            assert_eq!(game.board().state(), GameState::Playing);
+            assert!(game.board().revealed() > 9);
+            assert!(!rendering_context.damage().is_empty());
+            (pulls, messages)
+        };
+        let large = Options::custom(320, 320, 12000).unwrap();
+        let (pulls, messages) = handled(BEGINNER_OPTIONS);
+        assert_eq!(handled(large), (pulls, messages));
+        // the click and the render reach the game together, as one batch
+        assert!(pulls < messages);
+    }
+
+    #[test]
+    fn test_wiring() {
+        let game = Game::new(Layout::new(BEGINNER_OPTIONS));
+        assert!(game.wiring().validate().is_ok());
+        let dot = game.wiring().to_dot();
+        assert!(dot.starts_with("digraph wiring {\n    Grid -> Button;\n"));
+        assert!(dot.contains("    Game -> Grid;\n"));
+        assert!(dot.ends_with("    Minesweeper -> Game;\n}\n"));
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut game = Game::new(Layout::new(BEGINNER_OPTIONS));
+        game.trace().keep(100);
+        game.send(ChannelMessage::ToggleHeatmap);
+        let records: Vec<String> = game
+            .trace()
+            .records()
+            .iter()
+            .map(|record| record.to_string())
+            .collect();
+        assert_eq!(records.len(), 11);
+        assert_eq!(records[0], "1 received Minesweeper -> Game ToggleHeatmap");
+        assert_eq!(records[1], "2 sent Game -> Background ToggleHeatmap");
+        assert_eq!(records[5], "6 sent Game -> Grid ToggleHeatmap");
+        assert_eq!(records[6], "2 received Game -> Background ToggleHeatmap");
+        assert_eq!(records[10], "6 received Game -> Grid ToggleHeatmap");
+    }
 }
@@ -0,0 +1,296 @@
+// Golden image tests. Scripted games are played through Game and rendered with the SnapshotContext, and the
+// frames are compared with the reference images in tests/golden. After a change to the rendering, run the tests
+// with UPDATE_GOLDEN=1 to write the reference images again, and look them over before checking them in.
+use std::cell::RefCell;
+use std::env;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::config::{FirstClick, Layout, Options, BEGINNER_OPTIONS};
+use crate::game::Game;
+use crate::media_layer::{Frame, SnapshotContext};
+use crate::model::{CellView, GameState};
+use crate::sprites::ChannelMessage;
+use crate::sprites::{MouseAction, MouseButton, MouseEventData, Rect, RendererContext};
+use crate::testing::TestClock;
+
+const UPDATE_GOLDEN: &str = "UPDATE_GOLDEN";
+
+struct Harness {
+    layout: Layout,
+    game: Game,
+    context: Rc<Box<dyn RendererContext>>,
+    frame: Rc<RefCell<Frame>>,
+}
+
+impl Harness {
+    fn new(options: Options) -> Self {
+        let layout = Layout::new(options);
+        let context = SnapshotContext::new(layout);
+        let frame = context.frame();
+        // the clock doesn't move, so the timer always shows 000 however long the test takes
+        let game = Game::with_clock(layout, Rc::new(TestClock::new(Duration::from_secs(0))));
+        Self {
+            layout: layout,
+            game: game,
+            context: Rc::new(Box::new(context) as Box<dyn RendererContext>),
+            frame: frame,
+        }
+    }
+
+    fn send(&mut self, message: ChannelMessage) {
+        self.game.send(message);
+    }
+
+    fn mouse(&mut self, rect: Rect, mouse_btn: MouseButton, action: MouseAction) {
+        let event = MouseEventData {
+            x: rect.left() + rect.width() as i32 / 2,
+            y: rect.top() + rect.height() as i32 / 2,
+            mouse_btn: mouse_btn,
+            action: action,
+        };
+        self.send(ChannelMessage::MouseEvent(event));
+    }
+
+    fn click(&mut self, rect: Rect, mouse_btn: MouseButton) {
+        self.mouse(rect, mouse_btn, MouseAction::Click);
+    }
+
+    fn tile(&self, row: i16, column: i16) -> Rect {
+        let index = self.layout.options.index(row, column);
+        self.layout.grid_tile(index)
+    }
+
+    fn reveal(&mut self, row: i16, column: i16) {
+        self.click(self.tile(row, column), MouseButton::Left);
+    }
+
+    fn flag(&mut self, row: i16, column: i16) {
+        self.click(self.tile(row, column), MouseButton::Right);
+    }
+
+    fn new_game(&mut self) {
+        self.click(self.layout.face(), MouseButton::Left);
+    }
+
+    fn state(&self) -> GameState {
+        self.game.board().state()
+    }
+
+    // the hidden cells, a row at a time
+    fn hidden(&self) -> Vec<(i16, i16)> {
+        let options = self.layout.options;
+        let board = self.game.board();
+        (0..options.tiles() as u32)
+            .map(|index| options.row_column(index))
+            .filter(|(row, column)| board.cell(*row, *column) == CellView::Hidden)
+            .collect()
+    }
+
+    fn render(&mut self) -> Frame {
+        self.send(ChannelMessage::Render(Rc::clone(&self.context)));
+        self.frame.borrow().clone()
+    }
+
+    // render, and take what was drawn
+    fn damage(&mut self) -> Vec<Rect> {
+        self.render();
+        self.context.damage().take()
+    }
+
+    /// Render, and compare the frame with the reference image.
+    fn check(&mut self, name: &str) {
+        let frame = self.render();
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name]
+            .iter()
+            .collect::<PathBuf>()
+            .with_extension("png");
+        if env::var_os(UPDATE_GOLDEN).is_some() {
+            frame.save(&path).unwrap();
+            return;
+        }
+        let bytes = std::fs::read(&path).unwrap_or_else(|e| {
+            panic!(
+                "{}: {}, run with {}=1 to create it",
+                path.display(),
+                e,
+                UPDATE_GOLDEN
+            )
+        });
+        let expected = Frame::from_png(&bytes).unwrap();
+        let differences = frame.differences(&expected);
+        if differences > 0 {
+            let actual = env::temp_dir().join(format!("minesweeper_{}.png", name));
+            frame.save(&actual).unwrap();
+            panic!(
+                "{} pixels differ from {}, the frame is in {}",
+                differences,
+                path.display(),
+                actual.display()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_beginner() {
+    let options = BEGINNER_OPTIONS.with_first_click(FirstClick::Opening);
+    let mut harness = Harness::new(options.with_seed(1));
+    harness.check("beginner_new");
+    let new = harness.render();
+
+    harness.reveal(4, 4);
+    let (row, column) = harness.hidden()[0];
+    harness.flag(row, column);
+    assert_eq!(harness.state(), GameState::Playing);
+    harness.check("beginner_playing");
+
+    // a new game looks just like the first one
+    harness.new_game();
+    assert_eq!(harness.render(), new);
+}
+
+#[test]
+fn test_restore() {
+    // a restored game looks just like the one which was saved, the face, counters and tiles
+    let options = BEGINNER_OPTIONS.with_first_click(FirstClick::Opening);
+    let mut harness = Harness::new(options.with_seed(1));
+    harness.reveal(4, 4);
+    let (row, column) = harness.hidden()[0];
+    harness.flag(row, column);
+    let saved = harness.game.save();
+
+    let mut restored = Harness::new(saved.options());
+    restored.game.restore(&saved).unwrap();
+    assert_eq!(restored.state(), GameState::Playing);
+    assert_eq!(restored.render(), harness.render());
+}
+
+#[test]
+fn test_lose() {
+    // the mines which weren't found are shown, and the flags which were wrong
+    let mut harness = Harness::new(BEGINNER_OPTIONS.with_seed(2));
+    harness.reveal(0, 0);
+    let mines = harness.game.board().mines().unwrap();
+    let options = harness.layout.options;
+    let hidden = harness.hidden();
+    let wrong = hidden
+        .iter()
+        .find(|(row, column)| !mines.contains(&options.index(*row, *column)))
+        .cloned()
+        .unwrap();
+    let right = options.row_column(mines[0]);
+    harness.flag(wrong.0, wrong.1);
+    harness.flag(right.0, right.1);
+    for (row, column) in harness.hidden() {
+        harness.reveal(row, column);
+        if harness.state() == GameState::Lose {
+            break;
+        }
+    }
+    assert_eq!(harness.state(), GameState::Lose);
+    harness.check("beginner_lose");
+}
+
+#[test]
+fn test_win() {
+    // a narrow board, with the frame drawn around it, won with the opening
+    let options = Options::custom(4, 8, 3).unwrap();
+    let options = options.with_first_click(FirstClick::Opening);
+    let mut harness = Harness::new(options.with_seed(35));
+    harness.reveal(0, 0);
+    assert_eq!(harness.state(), GameState::Win);
+    harness.check("custom_win");
+}
+
+#[test]
+fn test_heatmap() {
+    // the hidden tiles are tinted by the chance of a mine, and turning it off leaves the board as it was
+    let options = BEGINNER_OPTIONS.with_first_click(FirstClick::Opening);
+    let mut harness = Harness::new(options.with_seed(1));
+    harness.reveal(4, 4);
+    let plain = harness.render();
+    harness.send(ChannelMessage::ToggleHeatmap);
+    harness.check("beginner_heatmap");
+    harness.send(ChannelMessage::ToggleHeatmap);
+    assert_eq!(harness.render(), plain);
+}
+
+#[test]
+fn test_hint() {
+    // the hint highlights a safe tile until the next move, and counts against the result
+    let options = BEGINNER_OPTIONS.with_first_click(FirstClick::Opening);
+    let mut harness = Harness::new(options.with_seed(1));
+    harness.reveal(4, 4);
+    let plain = harness.render();
+    harness.send(ChannelMessage::Hint);
+    harness.check("beginner_hint");
+    assert_eq!(harness.game.result().hints, 1);
+    // flag, question mark and hidden again
+    harness.flag(0, 0);
+    harness.flag(0, 0);
+    harness.flag(0, 0);
+    assert_eq!(harness.render(), plain);
+}
+
+#[test]
+fn test_pressed() {
+    // a held tile is pushed in and the face is surprised, and nothing happens unless it's let go of over the tile
+    let options = BEGINNER_OPTIONS.with_first_click(FirstClick::Opening);
+    let mut harness = Harness::new(options.with_seed(1));
+    harness.reveal(4, 4);
+    let plain = harness.render();
+    let (row, column) = harness.hidden()[0];
+    let (tile, away) = (harness.tile(row, column), harness.layout.face());
+    harness.mouse(tile, MouseButton::Left, MouseAction::Press);
+    harness.check("beginner_pressed");
+    harness.mouse(away, MouseButton::Left, MouseAction::Move);
+    harness.mouse(away, MouseButton::Left, MouseAction::Release);
+    assert_eq!(harness.render(), plain);
+    assert_eq!(harness.state(), GameState::Playing);
+
+    harness.mouse(tile, MouseButton::Left, MouseAction::Press);
+    harness.mouse(away, MouseButton::Left, MouseAction::Move);
+    harness.mouse(tile, MouseButton::Left, MouseAction::Move);
+    harness.mouse(tile, MouseButton::Left, MouseAction::Release);
+    assert_ne!(harness.game.board().cell(row, column), CellView::Hidden);
+}
+
+#[test]
+fn test_question_mark() {
+    // a second right click turns the flag into a question mark, which the flag counter doesn't count
+    let options = BEGINNER_OPTIONS.with_first_click(FirstClick::Opening);
+    let mut harness = Harness::new(options.with_seed(1));
+    harness.reveal(4, 4);
+    let (row, column) = harness.hidden()[0];
+    harness.flag(row, column);
+    harness.flag(row, column);
+    assert_eq!(harness.game.board().flags(), 0);
+    harness.check("beginner_question");
+}
+
+#[test]
+fn test_damage() {
+    // only what changed is drawn again, so a frame where nothing did draws nothing
+    let options = BEGINNER_OPTIONS.with_first_click(FirstClick::Opening);
+    let mut harness = Harness::new(options.with_seed(1));
+    let layout = harness.layout;
+    let window = Rect::new(0, 0, layout.width(), layout.height());
+    assert_eq!(harness.damage(), vec![window]);
+    assert!(harness.damage().is_empty());
+
+    harness.reveal(4, 4);
+    assert!(!harness.damage().is_empty());
+    let (row, column) = harness.hidden()[0];
+    harness.flag(row, column);
+    let flagged = harness.tile(row, column);
+    assert_eq!(harness.damage(), vec![layout.flag_digit_panel(), flagged]);
+
+    // the face is surprised while a tile is held
+    let (row, column) = harness.hidden()[0];
+    let tile = harness.tile(row, column);
+    harness.mouse(tile, MouseButton::Left, MouseAction::Press);
+    assert_eq!(harness.damage(), vec![layout.face(), tile]);
+    assert!(harness.damage().is_empty());
+}
@@ -0,0 +1,464 @@
+use snafu::OptionExt;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::config::Options;
+use crate::game::Game;
+use crate::sprites::ChannelMessage;
+use crate::sprites::{Error, InvalidReplay, MouseAction, MouseButton, MouseEventData};
+
+const HEADER: &str = "minesweeper replay";
+
+/// What the player did, a mouse event or asking for a hint.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ReplayInput {
+    Mouse(MouseEventData),
+    Hint,
+}
+
+/// An input, and when it happened relative to the start of the board.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct ReplayEvent {
+    time: Duration,
+    input: ReplayInput,
+}
+
+/// The events played on one board, which is placed from the seed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReplayBoard {
+    seed: u64,
+    events: Vec<ReplayEvent>,
+}
+
+/**
+ * Replay is a recording of the mouse events and hints of a session, a board at a time. Each board has its seed, so
+ * that playing the events again on the same board reproduces the game exactly. It is saved as text:
+ *
+ * minesweeper replay
+ * board <rows> <columns> <mines> <first click> [marks]
+ * seed <seed>
+ * <milliseconds> <x> <y> <left|middle|right> [press|move|release]
+ * <milliseconds> hint
+ * ...
+ *
+ * An event without a press, move or release is a click, a press and release at once. The board has marks when
+ * it was played with question marks, as they change what a right click does. Hints are recorded as they can
+ * reveal a tile.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    options: Options,
+    boards: Vec<ReplayBoard>,
+}
+
+impl Replay {
+    pub fn new(options: Options) -> Self {
+        Self {
+            options: options,
+            boards: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, Error> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Error::from(format!("{}: {}", path.display(), e)))?;
+        text.parse()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, self.to_string())
+            .map_err(|e| Error::from(format!("{}: {}", path.display(), e)))
+    }
+
+    /// The options to create the game with, the seed is the seed of the first board.
+    pub fn options(&self) -> Options {
+        match self.boards.first() {
+            Some(board) => self.options.with_seed(board.seed),
+            None => self.options,
+        }
+    }
+
+    /// Start recording a new board.
+    pub fn start_board(&mut self, seed: u64) {
+        self.boards.push(ReplayBoard {
+            seed: seed,
+            events: Vec::new(),
+        });
+    }
+
+    /// Record an event on the current board, time is since the board was started.
+    pub fn push(&mut self, time: Duration, event: MouseEventData) {
+        self.push_input(time, ReplayInput::Mouse(event));
+    }
+
+    /// Record a hint on the current board.
+    pub fn push_hint(&mut self, time: Duration) {
+        self.push_input(time, ReplayInput::Hint);
+    }
+
+    fn push_input(&mut self, time: Duration, input: ReplayInput) {
+        if let Some(board) = self.boards.last_mut() {
+            board.events.push(ReplayEvent {
+                time: time,
+                input: input,
+            });
+        }
+    }
+
+    /// The seed of the board being recorded.
+    pub fn seed(&self) -> Option<u64> {
+        self.boards.last().map(|board| board.seed)
+    }
+
+    /// The number of events recorded, on all of the boards.
+    pub fn len(&self) -> usize {
+        self.boards.iter().map(|board| board.events.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let options = &self.options;
+        writeln!(f, "{}", HEADER)?;
+        let marks = if options.question_marks() {
+            " marks"
+        } else {
+            ""
+        };
+        writeln!(
+            f,
+            "board {} {} {} {}{}",
+            options.rows,
+            options.columns,
+            options.mines(),
+            options.first_click().name(),
+            marks
+        )?;
+        for board in self.boards.iter() {
+            writeln!(f, "seed {}", board.seed)?;
+            for recorded in board.events.iter() {
+                let time = recorded.time.as_millis();
+                let event = match recorded.input {
+                    ReplayInput::Mouse(event) => event,
+                    ReplayInput::Hint => {
+                        writeln!(f, "{} hint", time)?;
+                        continue;
+                    }
+                };
+                let button = match event.mouse_btn {
+                    MouseButton::Left => "left",
+                    MouseButton::Middle => "middle",
+                    MouseButton::Right => "right",
+                };
+                let action = match event.action {
+                    MouseAction::Click => "",
+                    MouseAction::Press => " press",
+                    MouseAction::Move => " move",
+                    MouseAction::Release => " release",
+                };
+                writeln!(f, "{} {} {} {}{}", time, event.x, event.y, button, action)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+enum Line {
+    Header,
+    Board(Options),
+    Seed(u64),
+    Event(ReplayEvent),
+}
+
+fn parse_line(text: &str) -> Option<Line> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    match words.as_slice() {
+        ["minesweeper", "replay"] => Some(Line::Header),
+        ["board", rows, columns, mines, first_click, marks @ ..] => {
+            let options = Options::custom(
+                rows.parse().ok()?,
+                columns.parse().ok()?,
+                mines.parse().ok()?,
+            );
+            // replays from before question marks don't have them
+            let question_marks = match marks {
+                [] => false,
+                ["marks"] => true,
+                _ => return None,
+            };
+            Some(Line::Board(
+                options
+                    .ok()?
+                    .with_first_click(first_click.parse().ok()?)
+                    .with_question_marks(question_marks),
+            ))
+        }
+        ["seed", seed] => Some(Line::Seed(seed.parse().ok()?)),
+        [time, "hint"] => Some(Line::Event(ReplayEvent {
+            time: Duration::from_millis(time.parse().ok()?),
+            input: ReplayInput::Hint,
+        })),
+        [time, x, y, button, action @ ..] => {
+            let mouse_btn = match *button {
+                "left" => MouseButton::Left,
+                "middle" => MouseButton::Middle,
+                "right" => MouseButton::Right,
+                _ => return None,
+            };
+            let action = match action {
+                [] => MouseAction::Click,
+                ["press"] => MouseAction::Press,
+                ["move"] => MouseAction::Move,
+                ["release"] => MouseAction::Release,
+                _ => return None,
+            };
+            let event = MouseEventData {
+                x: x.parse().ok()?,
+                y: y.parse().ok()?,
+                mouse_btn: mouse_btn,
+                action: action,
+            };
+            Some(Line::Event(ReplayEvent {
+                time: Duration::from_millis(time.parse().ok()?),
+                input: ReplayInput::Mouse(event),
+            }))
+        }
+        _ => None,
+    }
+}
+
+impl FromStr for Replay {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Replay, Error> {
+        let mut header = false;
+        let mut replay: Option<Replay> = None;
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match (parse_line(line), replay.as_mut()) {
+                (Some(Line::Header), None) if !header => header = true,
+                (Some(Line::Board(options)), None) if header => replay = Some(Replay::new(options)),
+                (Some(Line::Seed(seed)), Some(replay)) => replay.start_board(seed),
+                (Some(Line::Event(event)), Some(replay)) if !replay.boards.is_empty() => {
+                    replay.push_input(event.time, event.input)
+                }
+                _ => {
+                    return InvalidReplay {
+                        line: index + 1,
+                        text: line,
+                    }
+                    .fail()
+                }
+            }
+        }
+        replay.context(InvalidReplay {
+            line: text.lines().count() + 1,
+            text: "",
+        })
+    }
+}
+
+/**
+ * ReplayPlayer plays a replay into a game, through the same MouseEvent and Hint messages the front ends send. The events
+ * are played when they are due, so the replay runs in real time, or faster or slower with the speed.
+ */
+pub struct ReplayPlayer {
+    replay: Replay,
+    speed: f64,
+    board: usize,
+    event: usize,
+    start: Option<Duration>,
+}
+
+impl ReplayPlayer {
+    /// Create a player, a speed of 1.0 is real time and 2.0 is twice as fast.
+    pub fn new(replay: Replay, speed: f64) -> Self {
+        Self {
+            replay: replay,
+            speed: speed,
+            board: 0,
+            event: 0,
+            start: None,
+        }
+    }
+
+    /// The options to create the game with.
+    pub fn options(&self) -> Options {
+        self.replay.options()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.board >= self.replay.boards.len()
+    }
+
+    /// Play the events which are due by now into the game, now being the time of a monotonic clock. Each board
+    /// is started with its recorded seed. Returns the number of events played.
+    pub fn play(&mut self, game: &mut Game, now: Duration) -> usize {
+        let mut played = 0;
+        while let Some(board) = self.replay.boards.get(self.board) {
+            let start = match self.start {
+                Some(start) => start,
+                None => {
+                    if game.seed() != board.seed {
+                        game.set_seed(board.seed);
+                    }
+                    self.start = Some(now);
+                    now
+                }
+            };
+            let elapsed = now.checked_sub(start).unwrap_or_default();
+            match board.events.get(self.event) {
+                Some(recorded)
+                    if recorded.time.as_secs_f64() <= elapsed.as_secs_f64() * self.speed =>
+                {
+                    let message = match recorded.input {
+                        ReplayInput::Mouse(event) => ChannelMessage::MouseEvent(event),
+                        ReplayInput::Hint => ChannelMessage::Hint,
+                    };
+                    game.send(message);
+                    self.event += 1;
+                    played += 1;
+                }
+                Some(_) => break,
+                None => {
+                    self.board += 1;
+                    self.event = 0;
+                    self.start = None;
+                }
+            }
+        }
+        played
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Replay, ReplayPlayer};
+    use crate::config::{FirstClick, Layout, Options, BEGINNER_OPTIONS};
+    use crate::game::Game;
+    use crate::model::GameState;
+    use crate::sprites::{ChannelMessage, MouseButton};
+    use crate::testing::{click, click_on, TestClock};
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_text() {
+        let text = "minesweeper replay\n\
+                    board 9 9 10 opening\n\
+                    seed 7\n\
+                    0 100 150 left\n\
+                    1500 25 90 right\n\
+                    1700 hint\n\
+                    seed 8\n\
+                    20 105 40 middle\n\
+                    30 105 40 left press\n\
+                    45 110 42 left move\n\
+                    60 110 42 left release\n";
+        let replay: Replay = text.parse().unwrap();
+        assert_eq!(replay.len(), 7);
+        assert_eq!(
+            replay.options(),
+            Options::custom(9, 9, 10)
+                .unwrap()
+                .with_first_click(FirstClick::Opening)
+                .with_question_marks(false)
+                .with_seed(7)
+        );
+        assert_eq!(replay.to_string(), text);
+
+        assert_eq!(
+            "minesweeper replay\nboard 9 9 10 safe\n10 1 1 left\n"
+                .parse::<Replay>()
+                .unwrap_err()
+                .to_string(),
+            "line 3 of the replay isn't valid: \"10 1 1 left\""
+        );
+        assert!(
+            "minesweeper replay\nboard 9 9 10 safe\nseed 1\n10 1 1 left drag\n"
+                .parse::<Replay>()
+                .is_err()
+        );
+        assert!("board 9 9 10 safe\n".parse::<Replay>().is_err());
+        assert!("minesweeper replay\n".parse::<Replay>().is_err());
+        assert!("minesweeper replay\nboard 9 9 100 safe\n"
+            .parse::<Replay>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_record_and_play() {
+        let clock = Rc::new(TestClock::new(Duration::from_secs(10)));
+        let layout = Layout::new(BEGINNER_OPTIONS);
+        let mut game = Game::with_clock(layout, clock.clone());
+        game.record();
+
+        // reveal, lose, and start another board
+        click(&mut game, 40, MouseButton::Left);
+        clock.set(Duration::from_millis(12_500));
+        for index in 0..81 {
+            click(&mut game, index, MouseButton::Left);
+        }
+        assert_eq!(game.board().state(), GameState::Lose);
+        clock.set(Duration::from_secs(20));
+        click_on(&mut game, layout.face(), MouseButton::Left);
+        click(&mut game, 0, MouseButton::Right);
+
+        let replay = game.replay().unwrap().clone();
+        assert_eq!(replay.len(), 84);
+        let replay: Replay = replay.to_string().parse().unwrap();
+        assert!(replay.to_string().contains("\n2500 15 81 left\n"));
+        assert!(replay.to_string().contains("\n0 15 81 right\n"));
+        assert!(replay.to_string().contains("\nboard 9 9 10 safe marks\n"));
+
+        // played at twice the speed, the boards come out the same
+        let clock = Rc::new(TestClock::new(Duration::from_secs(0)));
+        let mut played = Game::with_clock(Layout::new(replay.options()), clock.clone());
+        let mut player = ReplayPlayer::new(replay.clone(), 2.0);
+        assert_eq!(player.play(&mut played, Duration::from_millis(0)), 1);
+        assert_eq!(player.play(&mut played, Duration::from_millis(1249)), 0);
+        assert_eq!(played.board().state(), GameState::Playing);
+        assert_eq!(player.play(&mut played, Duration::from_millis(1250)), 81);
+        assert_eq!(played.board().state(), GameState::Lose);
+        assert!(!player.is_finished());
+        assert_eq!(player.play(&mut played, Duration::from_secs(60)), 2);
+        assert!(player.is_finished());
+        assert_eq!(played.seed(), game.seed());
+        assert_eq!(played.board().flags(), 1);
+    }
+
+    #[test]
+    fn test_hints() {
+        let clock = Rc::new(TestClock::new(Duration::from_secs(0)));
+        let layout = Layout::new(BEGINNER_OPTIONS.with_seed(1));
+        let mut game = Game::with_clock(layout, clock.clone());
+        game.record();
+        click(&mut game, 0, MouseButton::Left);
+        clock.set(Duration::from_secs(1));
+        for _ in 0..3 {
+            game.send(ChannelMessage::Hint);
+        }
+        assert_eq!(game.result().hints, 3);
+
+        // the hints are played back, so the same tiles are revealed
+        let replay: Replay = game.replay().unwrap().to_string().parse().unwrap();
+        assert_eq!(replay.len(), 4);
+        assert!(replay
+            .to_string()
+            .ends_with("\n1000 hint\n1000 hint\n1000 hint\n"));
+        let mut played = Game::with_clock(Layout::new(replay.options()), clock.clone());
+        let mut player = ReplayPlayer::new(replay, 1.0);
+        assert_eq!(player.play(&mut played, Duration::from_secs(0)), 1);
+        assert_eq!(player.play(&mut played, Duration::from_secs(1)), 3);
+        assert_eq!(played.board().to_string(), game.board().to_string());
+        assert_eq!(played.result().hints, 3);
+    }
+}
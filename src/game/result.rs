@@ -0,0 +1,72 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::model::GameState;
+
+// each hint adds this to the time the board took
+const HINT_PENALTY: Duration = Duration::from_secs(10);
+
+/// How a board was left: its state, the time it has been played for and the hints the player was given.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GameResult {
+    pub state: GameState,
+    pub elapsed: Duration,
+    pub hints: u32,
+}
+
+impl GameResult {
+    /// The time added for the hints.
+    pub fn penalty(&self) -> Duration {
+        HINT_PENALTY * self.hints as u32
+    }
+
+    /// The time the board took, with the penalty for the hints.
+    pub fn time(&self) -> Duration {
+        self.elapsed + self.penalty()
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = match self.state {
+            GameState::Win => "won",
+            GameState::Lose => "lost",
+            _ => "playing",
+        };
+        write!(f, "{} in {} seconds", state, self.time().as_secs())?;
+        if self.hints > 0 {
+            write!(
+                f,
+                ", {} of them for {} hints",
+                self.penalty().as_secs(),
+                self.hints
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GameResult;
+    use crate::model::GameState;
+    use std::time::Duration;
+
+    #[test]
+    fn test_penalty() {
+        let mut result = GameResult {
+            state: GameState::Win,
+            elapsed: Duration::from_millis(42_500),
+            hints: 0,
+        };
+        assert_eq!(result.time(), result.elapsed);
+        assert_eq!(result.to_string(), "won in 42 seconds");
+
+        result.hints = 2;
+        assert_eq!(result.time(), Duration::from_millis(62_500));
+        assert_eq!(
+            result.to_string(),
+            "won in 62 seconds, 20 of them for 2 hints"
+        );
+    }
+}
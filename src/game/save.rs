@@ -0,0 +1,326 @@
+use snafu::{ensure, OptionExt};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::config::Options;
+use crate::model::{Board, CellView, GameState};
+use crate::sprites::{Error, InvalidSave};
+
+const HEADER: &str = "minesweeper save";
+
+/**
+ * SavedGame is a board as the player left it: where the mines are, what has been revealed and flagged, how long
+ * it has been played for and the state of the game. It is saved as text, with the rows of the board written as
+ * the board writes itself, so they can be loaded as a board too:
+ *
+ * minesweeper save
+ * board <rows> <columns> <mines> <first click>
+ * seed <seed>
+ * state <init|playing|win|lose>
+ * elapsed <milliseconds>
+ * hints <hints>
+ * .*1F..
+ *
+ * There isn't a hints line until a hint is given, and the rows don't have any mines until the first reveal
+ * places them.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedGame {
+    options: Options,
+    seed: u64,
+    state: GameState,
+    elapsed: Duration,
+    hints: u32,
+    mines: Option<Vec<u32>>,
+    cells: Vec<CellView>,
+}
+
+impl SavedGame {
+    /// Save the board, which has been played for elapsed.
+    pub fn new(board: &Board, elapsed: Duration) -> Self {
+        let options = *board.options();
+        Self {
+            options: options,
+            seed: board.seed(),
+            state: board.state(),
+            elapsed: elapsed,
+            hints: board.hints(),
+            mines: board.mines(),
+            cells: (0..options.tiles() as u32)
+                .map(|index| board.cell_at(index))
+                .collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<SavedGame, Error> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Error::from(format!("{}: {}", path.display(), e)))?;
+        text.parse()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, self.to_string())
+            .map_err(|e| Error::from(format!("{}: {}", path.display(), e)))
+    }
+
+    /// The options to create the game with, the seed is the seed of the saved board.
+    pub fn options(&self) -> Options {
+        self.options.with_seed(self.seed)
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Create the board as it was saved.
+    pub fn board(&self) -> Result<Board, Error> {
+        let board = self.grid()?.with_hints(self.hints);
+        ensure!(
+            board.state() == self.state,
+            InvalidSave {
+                reason: format!(
+                    "the board is {}, not {}",
+                    board.state().name(),
+                    self.state.name()
+                )
+            }
+        );
+        Ok(board)
+    }
+}
+
+impl SavedGame {
+    // the board the rows are written from, without checking its state
+    fn grid(&self) -> Result<Board, Error> {
+        Board::restore(self.options, self.seed, self.mines.as_deref(), &self.cells)
+    }
+}
+
+impl fmt::Display for SavedGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let options = &self.options;
+        writeln!(f, "{}", HEADER)?;
+        writeln!(
+            f,
+            "board {} {} {} {}",
+            options.rows,
+            options.columns,
+            options.mines(),
+            options.first_click().name()
+        )?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "state {}", self.state.name())?;
+        writeln!(f, "elapsed {}", self.elapsed.as_millis())?;
+        if self.hints > 0 {
+            writeln!(f, "hints {}", self.hints)?;
+        }
+        // a saved game is always one that can be played, so it has a board to write
+        write!(f, "{}", self.grid().map_err(|_| fmt::Error)?)
+    }
+}
+
+// a line of the file, numbered from 1
+type Line<'a> = (usize, &'a str);
+
+fn invalid<T>((number, text): Line) -> Result<T, Error> {
+    InvalidSave {
+        reason: format!("line {} is {:?}", number, text),
+    }
+    .fail()
+}
+
+fn parse<T: FromStr>(line: Line, value: &str) -> Result<T, Error> {
+    match value.parse() {
+        Ok(value) => Ok(value),
+        Err(_) => invalid(line),
+    }
+}
+
+// the next line, which should be the name followed by its values
+fn field<'a>(
+    lines: &mut dyn Iterator<Item = Line<'a>>,
+    name: &str,
+) -> Result<(Line<'a>, Vec<&'a str>), Error> {
+    let line = lines.next().context(InvalidSave {
+        reason: format!("the {} is missing", name),
+    })?;
+    let mut words = line.1.split_whitespace();
+    match words.next() {
+        Some(word) if word == name => Ok((line, words.collect())),
+        _ => invalid(line),
+    }
+}
+
+// the next line, which should be the name followed by one value
+fn value<'a, T: FromStr>(
+    lines: &mut dyn Iterator<Item = Line<'a>>,
+    name: &str,
+) -> Result<T, Error> {
+    match field(lines, name)? {
+        (line, values) if values.len() == 1 => parse(line, values[0]),
+        (line, _) => invalid(line),
+    }
+}
+
+impl FromStr for SavedGame {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<SavedGame, Error> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, text)| (index + 1, text))
+            .filter(|(_, text)| !text.trim().is_empty())
+            .peekable();
+        match field(&mut lines, "minesweeper")? {
+            (_, values) if values == ["save"] => (),
+            (line, _) => return invalid(line),
+        }
+        let options = match field(&mut lines, "board")? {
+            (line, values) if values.len() == 4 => {
+                let rows = parse(line, values[0])?;
+                let columns = parse(line, values[1])?;
+                let mines = parse(line, values[2])?;
+                let first_click = parse(line, values[3])?;
+                Options::custom(rows, columns, mines)?.with_first_click(first_click)
+            }
+            (line, _) => return invalid(line),
+        };
+        let seed = value(&mut lines, "seed")?;
+        let state = value(&mut lines, "state")?;
+        let elapsed = Duration::from_millis(value(&mut lines, "elapsed")?);
+        let hints = match lines.peek() {
+            Some((_, text)) if text.starts_with("hints") => value(&mut lines, "hints")?,
+            _ => 0,
+        };
+
+        // the rows are a board, which has mines once they've been placed
+        let rows: Vec<&str> = lines.map(|(_, text)| text).collect();
+        let grid: Board = rows.join("\n").parse()?;
+        let size = (grid.options().rows, grid.options().columns);
+        ensure!(
+            size == (options.rows, options.columns),
+            InvalidSave {
+                reason: format!(
+                    "the rows are {} by {}, the board is {} by {}",
+                    size.0, size.1, options.rows, options.columns
+                )
+            }
+        );
+        let mines = grid
+            .mines()
+            .filter(|mines| !mines.is_empty() || options.mines() == 0);
+        let cells: Vec<CellView> = (0..options.tiles() as u32)
+            .map(|index| grid.cell_at(index))
+            .collect();
+
+        let saved = SavedGame {
+            options: options,
+            seed: seed,
+            state: state,
+            elapsed: elapsed,
+            hints: hints,
+            mines: mines,
+            cells: cells,
+        };
+        // make sure it can be played
+        saved.board()?;
+        Ok(saved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SavedGame;
+    use crate::config::{FirstClick, Options, BEGINNER_OPTIONS};
+    use crate::model::{Board, GameState};
+    use std::time::Duration;
+
+    #[test]
+    fn test_text() {
+        let mut board = Board::with_mines(
+            Options::custom(3, 4, 2)
+                .unwrap()
+                .with_first_click(FirstClick::Any),
+            &[0, 11],
+        );
+        board.reveal(0, 3);
+        board.toggle_flag(0, 0);
+        board.mark(2, 0);
+        board.mark(2, 0);
+        let saved = SavedGame::new(&board, Duration::from_millis(12_345));
+        let text = "minesweeper save\n\
+                    board 3 4 2 any\n\
+                    seed 0\n\
+                    state playing\n\
+                    elapsed 12345\n\
+                    F100\n\
+                    .111\n\
+                    q..*\n";
+        assert_eq!(saved.to_string(), text);
+        // the rows are the board as it writes itself
+        assert!(text.ends_with(&board.to_string()));
+        assert_eq!(text.parse::<SavedGame>().unwrap(), saved);
+        assert_eq!(saved.state(), GameState::Playing);
+        assert_eq!(saved.elapsed(), Duration::from_millis(12_345));
+        assert_eq!(saved.board().unwrap().revealed(), 6);
+
+        // the hints given are kept, as they're a penalty
+        board.add_hint();
+        let saved = SavedGame::new(&board, Duration::from_millis(12_345));
+        let with_hints = text.replace("elapsed 12345\n", "elapsed 12345\nhints 1\n");
+        assert_eq!(saved.to_string(), with_hints);
+        assert_eq!(with_hints.parse::<SavedGame>().unwrap(), saved);
+        assert_eq!(saved.board().unwrap().hints(), 1);
+
+        // a board before the first reveal has no mines
+        let board = Board::new(BEGINNER_OPTIONS.with_seed(4));
+        let saved = SavedGame::new(&board, Duration::default());
+        assert!(!saved.to_string().contains('*'));
+        let parsed: SavedGame = saved.to_string().parse().unwrap();
+        assert_eq!(parsed.options().seed(), Some(4));
+        assert_eq!(parsed.board().unwrap().state(), GameState::Init);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let error = |text: &str| text.parse::<SavedGame>().unwrap_err().to_string();
+        let valid = "minesweeper save\nboard 1 3 1 safe\nseed 1\nstate playing\nelapsed 5\n*1.\n";
+        assert!(valid.parse::<SavedGame>().is_ok());
+        assert_eq!(
+            error(&valid.replace("seed 1", "seed x")),
+            "the saved game isn't valid, line 3 is \"seed x\""
+        );
+        assert_eq!(
+            error(&valid.replace("state playing", "state win")),
+            "the saved game isn't valid, the board is playing, not win"
+        );
+        assert_eq!(
+            error(&valid.replace("*1.", "*2.")),
+            "the board isn't valid, tile 1 doesn't agree with the mines"
+        );
+        assert_eq!(
+            error(&valid.replace("elapsed 5\n", "")),
+            "the saved game isn't valid, line 5 is \"*1.\""
+        );
+        assert_eq!(
+            error(&valid.replace("*1.\n", "")),
+            "the board isn't valid, there aren't any rows"
+        );
+        assert_eq!(
+            error(&valid.replace("*1.", "*1")),
+            "the saved game isn't valid, the rows are 1 by 2, the board is 1 by 3"
+        );
+        assert_eq!(
+            error(&valid.replace("*1.", "?1.")),
+            "the board isn't valid, line 1 has '?'"
+        );
+        assert!(error("minesweeper saved\n").contains("line 1"));
+    }
+}
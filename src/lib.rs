@@ -1,75 +1,333 @@
-#[cfg(not(any(
-    feature = "media_layer_sdl2",
-    feature = "media_layer_text",
-    feature = "media_layer_wasm"
-)))]
-std::compile_error!("Either feature \"media_layer_sdl2\", feature \"media_layer_text\", or \"media_layer_wasm\" must be enabled for this crate.");
-
-#[cfg(feature = "media_layer_sdl2")]
-#[cfg(any(feature = "media_layer_text", feature = "media_layer_wasm"))]
-std::compile_error!(
-    "Either feature \"media_layer_sdl2\", should not be configured with other media_layers."
-);
-
-#[cfg(feature = "media_layer_text")]
-#[cfg(any(feature = "media_layer_sdl2", feature = "media_layer_wasm"))]
-std::compile_error!(
-    "Either feature \"media_layer_text\", should not be configured with other media_layers."
-);
-
-#[cfg(feature = "media_layer_wasm")]
-#[cfg(any(feature = "media_layer_sdl2", feature = "media_layer_text"))]
-std::compile_error!(
-    "Either feature \"media_layer_wasm\", should not be configured with other media_layers."
-);
-
 mod config;
 mod game;
 mod media_layer;
+mod model;
+mod solver;
 mod sprites;
+#[cfg(test)]
+mod testing;
 
 /**
  * The library contains most of the game logic. There is very little that needs to be exposed to the from end.
  * A rendering context is passed around which is used in generating the UI updates.
  */
 pub use crate::config::Layout;
-pub use crate::game::Game;
-pub use crate::sprites::{Error, MouseButton, MouseEventData};
-pub use crate::sprites::{Renderer, RendererContext};
+pub use crate::config::{FirstClick, Options, BEGINNER_OPTIONS, EXPERT_OPTIONS, INTERMEDIATE_OPTIONS};
+pub use crate::game::{Game, GameResult, Replay, ReplayPlayer, SavedGame};
+pub use crate::model::{Board, CellView, GameState};
+pub use crate::solver::{Probabilities, Solution, Solver};
+pub use crate::sprites::{Error, MouseAction, MouseButton, MouseButtons, MouseEventData};
+pub use crate::sprites::{Damage, Renderer, RendererContext};
+
+pub use crate::media_layer::{Frame, HeadlessContext, SnapshotContext, Texture, TextureManager};
+pub use crate::media_layer::{text_rects, DIGITS, GLYPH_ADVANCE, LINE_HEIGHT, TILES};
 
-pub use crate::media_layer::{ResourceContainer, Texture, TextureManager};
+#[cfg(feature = "media_layer_sdl2")]
+pub use crate::media_layer::Sdl2Container;
+#[cfg(feature = "media_layer_text")]
+pub use crate::media_layer::TextContainer;
+#[cfg(feature = "media_layer_wasm")]
+pub use crate::media_layer::WebImageContainer;
 
-pub use crate::sprites::Rect;
-pub use crate::sprites::{ChannelMessage, MessageExchange};
+pub use crate::sprites::{Clock, Color, InstantClock, Point, Rect};
+pub use crate::sprites::{ChannelMessage, ChannelWiring, MessageExchange, Sender};
+pub use crate::sprites::{Trace, TraceEvent, TraceRecord};
 
 #[cfg(feature = "media_layer_text")]
-mod text {
+pub mod text {
     use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const HEADER_WIDTH: usize = 11; //< the flag counter, the face and the timer, with room between them
+    const GRID_LINE: usize = 2; //< the header and a blank line are above the grid
+
+    /**
+     * The screen is the terminal as lines of characters. Images are drawn as their glyph, at the character
+     * position that matches where the layout would have put them.
+     */
+    struct Screen {
+        layout: Layout,
+        lines: Vec<Vec<char>>,
+    }
+
+    impl Screen {
+        fn new(layout: Layout) -> Self {
+            let width = std::cmp::max(2 * layout.options.columns as usize - 1, HEADER_WIDTH);
+            let height = GRID_LINE + layout.options.rows as usize;
+            Self {
+                layout: layout,
+                lines: vec![vec![' '; width]; height],
+            }
+        }
+
+        fn width(&self) -> usize {
+            self.lines[0].len()
+        }
+
+        fn grid_left(&self) -> usize {
+            (self.width() - (2 * self.layout.options.columns as usize - 1)) / 2
+        }
+
+        /// The line and character of the tile at row, column.
+        fn tile_position(&self, row: i16, column: i16) -> (usize, usize) {
+            (
+                GRID_LINE + row as usize,
+                self.grid_left() + 2 * column as usize,
+            )
+        }
+
+        // where the layout's rect is on the screen, things without a place aren't drawn
+        fn position(&self, rect: Rect) -> Option<(usize, usize)> {
+            let layout = &self.layout;
+            let grid = layout.grid();
+            if grid.contains_point((rect.left(), rect.top())) {
+                let side = Layout::tile_side() as i32;
+                let column = (rect.left() - grid.left()) / side;
+                let row = (rect.top() - grid.top()) / side;
+                return Some(self.tile_position(row as i16, column as i16));
+            }
+            if rect == layout.face() {
+                return Some((0, self.width() / 2 - 1));
+            }
+            (0..3).find_map(|position| {
+                if rect == layout.flag_digit(position) {
+                    Some((0, position as usize))
+                } else if rect == layout.timer_digit(position) {
+                    Some((0, self.width() - 3 + position as usize))
+                } else {
+                    None
+                }
+            })
+        }
+
+        fn draw(&mut self, glyph: &str, rect: Rect) {
+            if let Some((line, start)) = self.position(rect) {
+                for (offset, character) in glyph.chars().enumerate() {
+                    self.lines[line][start + offset] = character;
+                }
+            }
+        }
+
+        fn to_strings(&self) -> Vec<String> {
+            self.lines
+                .iter()
+                .map(|line| line.iter().collect::<String>().trim_end().to_string())
+                .collect()
+        }
+    }
+
+    struct RenderingContext {
+        layout: Layout,
+        screen: Rc<RefCell<Screen>>,
+        texture_manager: TextureManager,
+        damage: Damage,
+    }
+
+    impl RendererContext for RenderingContext {
+        fn render_image(
+            &self,
+            texture: &Texture,
+            _src: Option<Rect>,
+            dst: Rect,
+        ) -> Result<(), String> {
+            if let Some(text) = texture.take_resource::<TextContainer>() {
+                self.screen.borrow_mut().draw(text.glyph, dst);
+                texture.set_resource(text);
+                self.damage.add(dst);
+            }
+            Ok(())
+        }
+
+        // the frame isn't drawn in a terminal
+        fn fill_rect(&self, _rect: Rect, _color: Color) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn layout(&self) -> &Layout {
+            &self.layout
+        }
+
+        fn load(&self, name: &str) -> Result<Rc<Texture>, String> {
+            let texture = self.texture_manager.load(name)?;
+            if !texture.has_image_data() {
+                texture.set_resource(TextContainer::new(name));
+            }
+            Ok(texture)
+        }
+
+        fn load_digit(&self, value: u64) -> Result<Rc<Texture>, String> {
+            self.load(&format!("digit_{}", DIGITS[value as usize]))
+        }
+
+        fn load_tile(&self, value: u64) -> Result<Rc<Texture>, String> {
+            self.load(&format!("tile_{}", TILES[value as usize]))
+        }
+
+        fn damage(&self) -> &Damage {
+            &self.damage
+        }
+
+        fn end_rendering(&self) {
+            self.damage.take();
+        }
+    }
+
+    /// What the player can do from the keyboard.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum Command {
+        Up,
+        Down,
+        Left,
+        Right,
+        Reveal,
+        Flag,
+        Chord,
+        NewGame,
+    }
+
+    /**
+     * The terminal front end. The player moves a cursor around the grid, and the commands are played as mouse
+     * events on the tile under the cursor, so the game runs just as it does with a mouse. Rendering fills in
+     * a screen of characters, which the caller prints however it likes.
+     */
     pub struct Minesweeper {
-        pub layout: Rc<Layout>,
-        pub game: Game,
-        pub texture_manager: TextureManager,
-        pub digits: Vec<String>,
-        pub tiles: Vec<String>,
+        layout: Layout,
+        game: Game,
+        cursor: (i16, i16),
+        rendering_context: Rc<Box<dyn RendererContext>>,
+        screen: Rc<RefCell<Screen>>,
     }
+
     impl Minesweeper {
-        fn new() -> Self {
-            let layout = Rc::new(config::BEGINNER_LAYOUT);
-
-            let digits = [
-                "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
-            ];
-            let tiles = [
-                "none", "one", "two", "three", "four", "five", "six", "seven", "eight",
-            ];
+        pub fn new(options: Options) -> Self {
+            let layout = Layout::new(options);
+            let screen = Rc::new(RefCell::new(Screen::new(layout)));
+
+            let rendering_context = RenderingContext {
+                layout: layout,
+                screen: screen.clone(),
+                texture_manager: TextContainer::new_texture_manager(),
+                damage: Damage::default(),
+            };
+            let context = Rc::new(Box::new(rendering_context) as Box<dyn RendererContext>);
+            let game = Game::new(layout);
             Self {
-                layout: layout.clone(),
-                game: Game::new(&layout),
-                texture_manager: TextureManager::new(),
-                digits: digits.iter().map(|s| s.to_string()).collect(),
-                tiles: tiles.iter().map(|s| s.to_string()).collect(),
+                layout: layout,
+                game: game,
+                cursor: (0, 0),
+                rendering_context: context,
+                screen: screen,
+            }
+        }
+
+        pub fn seed(&self) -> u64 {
+            self.game.seed()
+        }
+
+        /// The game being played, for recording it and playing replays into it.
+        pub fn game(&mut self) -> &mut Game {
+            &mut self.game
+        }
+
+        /// Render the game, and get the lines of the screen.
+        pub fn render(&mut self) -> Vec<String> {
+            let message = ChannelMessage::Render(Rc::clone(&self.rendering_context));
+            self.game.send(message);
+            self.rendering_context.end_rendering();
+            self.screen.borrow().to_strings()
+        }
+
+        /// The line and character of the cursor on the screen.
+        pub fn cursor(&self) -> (usize, usize) {
+            let (row, column) = self.cursor;
+            self.screen.borrow().tile_position(row, column)
+        }
+
+        pub fn handle_command(&mut self, command: Command) {
+            let (row, column) = self.cursor;
+            let options = self.layout.options;
+            match command {
+                Command::Up => self.cursor.0 = (row - 1).max(0),
+                Command::Down => self.cursor.0 = (row + 1).min(options.rows - 1),
+                Command::Left => self.cursor.1 = (column - 1).max(0),
+                Command::Right => self.cursor.1 = (column + 1).min(options.columns - 1),
+                Command::Reveal => self.click(self.tile(), MouseButton::Left),
+                Command::Flag => self.click(self.tile(), MouseButton::Right),
+                Command::Chord => self.click(self.tile(), MouseButton::Middle),
+                Command::NewGame => self.click(self.layout.face(), MouseButton::Left),
             }
         }
+
+        fn tile(&self) -> Rect {
+            let (row, column) = self.cursor;
+            let index = self.layout.options.index(row, column);
+            self.layout.grid_tile(index)
+        }
+
+        fn click(&mut self, rect: Rect, mouse_btn: MouseButton) {
+            let event = MouseEventData {
+                x: rect.left() + rect.width() as i32 / 2,
+                y: rect.top() + rect.height() as i32 / 2,
+                mouse_btn: mouse_btn,
+                action: MouseAction::Click,
+            };
+            self.game.send(ChannelMessage::MouseEvent(event));
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{Command, Minesweeper};
+        use crate::config::{FirstClick, Options, BEGINNER_OPTIONS};
+
+        #[test]
+        fn test_render() {
+            let mut minesweeper = Minesweeper::new(BEGINNER_OPTIONS.with_seed(1));
+            let screen = minesweeper.render();
+            assert_eq!(screen.len(), 11);
+            assert_eq!(screen[0], "010    :)     000");
+            assert_eq!(screen[1], "");
+            assert_eq!(screen[2], ". . . . . . . . .");
+            assert_eq!(minesweeper.cursor(), (2, 0));
+
+            // narrow boards are centered under the header
+            let mut minesweeper = Minesweeper::new(Options::custom(2, 3, 1).unwrap());
+            let screen = minesweeper.render();
+            assert_eq!(screen[0], "001 :)  000");
+            assert_eq!(screen[2], "   . . .");
+            assert_eq!(minesweeper.cursor(), (2, 3));
+        }
+
+        #[test]
+        fn test_commands() {
+            let options = BEGINNER_OPTIONS.with_first_click(FirstClick::Opening);
+            let mut minesweeper = Minesweeper::new(options.with_seed(1));
+            minesweeper.handle_command(Command::Flag);
+            let screen = minesweeper.render();
+            assert_eq!(screen[0], "009    :)     000");
+            assert!(screen[2].starts_with("F ."));
+            minesweeper.handle_command(Command::Flag);
+
+            // the cursor stays on the board
+            minesweeper.handle_command(Command::Up);
+            minesweeper.handle_command(Command::Left);
+            assert_eq!(minesweeper.cursor(), (2, 0));
+            for _ in 0..4 {
+                minesweeper.handle_command(Command::Down);
+                minesweeper.handle_command(Command::Right);
+            }
+            assert_eq!(minesweeper.cursor(), (6, 8));
+
+            // the first reveal opens an area
+            minesweeper.handle_command(Command::Reveal);
+            let screen = minesweeper.render();
+            assert_eq!(screen[6].chars().nth(8), Some(' '));
+
+            minesweeper.handle_command(Command::NewGame);
+            let screen = minesweeper.render();
+            assert_eq!(screen[6], ". . . . . . . . .");
+        }
     }
 }
 
@@ -94,8 +352,9 @@ mod wasm {
 
     use media_layer::TextureManager;
 
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
     use std::rc::Rc;
+    use std::time::Duration;
 
     // A macro to provide `println!(..)`-style syntax for `console.log` logging.
     macro_rules! log {
@@ -108,13 +367,12 @@ mod wasm {
         canvas: Rc<web_sys::CanvasRenderingContext2d>,
         layout: Layout,
          texture_manager: TextureManager,
-        digits: Vec<String>,
-        tiles: Vec<String>,
+        damage: Damage,
     }
 
     impl RenderingContext {
 
-        fn render_from_cache(&self, resource: &ResourceContainer, left: i32, top: i32) {
+        fn render_from_cache(&self, resource: &WebImageContainer, left: i32, top: i32) {
             self.canvas
                 .put_image_data(resource.get_image_data(), left as f64, top as f64);
         }
@@ -127,20 +385,40 @@ mod wasm {
             _src: Option<Rect>,
             dst: Rect,
         ) -> Result<(), String> {
-            match texture.image_data.take() {
+            match texture.take_resource::<WebImageContainer>() {
                 Some(web_image) => {
                     self.render_from_cache(&web_image, dst.left(), dst.top());
-                    texture.image_data.set(Some(web_image));
+                    texture.set_resource(web_image);
                 }
                 None => match texture.raw_bytes() {
                     Some(png) => {
-                        let resource = ResourceContainer::new(png, dst.width(), dst.height());
+                        let resource = WebImageContainer::new(png, dst.width(), dst.height());
                         self.render_from_cache(&resource, dst.left(), dst.top());
-                        texture.image_data.set(Some(resource));
+                        texture.set_resource(resource);
                     }
                     None => log!("unable to load png from memory"),
                 },
             };
+            self.damage.add(dst);
+            Ok(())
+        }
+
+        fn fill_rect(&self, rect: Rect, color: Color) -> Result<(), String> {
+            let style = format!(
+                "rgba({}, {}, {}, {})",
+                color.r,
+                color.g,
+                color.b,
+                color.a as f64 / 255.0
+            );
+            self.canvas.set_fill_style(&JsValue::from_str(&style));
+            self.canvas.fill_rect(
+                rect.left() as f64,
+                rect.top() as f64,
+                rect.width() as f64,
+                rect.height() as f64,
+            );
+            self.damage.add(rect);
             Ok(())
         }
 
@@ -153,53 +431,69 @@ mod wasm {
         }
 
         fn load_digit(&self, value: u64) -> Result<Rc<Texture>, String> {
-            let name = &self.digits[value as usize];
-            let image_name = format!("digit_{}", name);
-            self.load(&image_name)
+            self.load(&format!("digit_{}", DIGITS[value as usize]))
         }
 
         fn load_tile(&self, value: u64) -> Result<Rc<Texture>, String> {
-            let name = &self.tiles[value as usize];
-            let image_name = format!("tile_{}", name);
-            self.load(&image_name)
+            self.load(&format!("tile_{}", TILES[value as usize]))
+        }
+
+        fn damage(&self) -> &Damage {
+            &self.damage
+        }
+
+        // the canvas keeps what was drawn on it, so there's nothing more to show
+        fn end_rendering(&self) {
+            self.damage.take();
         }
+    }
 
-        fn end_rendering(&self) {}
+    // the page's monotonic clock, the time since the page was loaded
+    struct PerformanceClock {
+        performance: web_sys::Performance,
+    }
+
+    impl Clock for PerformanceClock {
+        fn now(&self) -> Duration {
+            Duration::from_secs_f64(self.performance.now() / 1000.0)
+        }
     }
 
     pub struct Minesweeper {
         layout: Layout,
         game: RefCell<Game>,
-        game_sender: std::sync::mpsc::Sender<ChannelMessage>,
+        seed: Cell<u64>,
         rendering_context: Rc<Box<dyn RendererContext>>,
     }
 
     impl Minesweeper {
         pub fn new(canvas: &Rc<web_sys::CanvasRenderingContext2d>) -> Self {
-            let layout = config::BEGINNER_LAYOUT;
-
-            let digits = [
-                "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
-            ];
-            let tiles = [
-                "none", "one", "two", "three", "four", "five", "six", "seven", "eight",
-            ];
-            
+            let mut options = config::BEGINNER_OPTIONS;
+            if let Some(seed) = seed_from_location() {
+                options = options.with_seed(seed);
+            }
+            if query("question-marks").as_deref() == Some("off") {
+                options = options.with_question_marks(false);
+            }
+            let layout = Layout::new(options);
 
             let rendering_context = RenderingContext {
                 canvas: canvas.clone(),
                 layout: layout,
-                texture_manager: ResourceContainer::new_texture_manager(),
-                digits: digits.iter().map(|s| s.to_string()).collect(),
-                tiles: tiles.iter().map(|s| s.to_string()).collect(),
+                texture_manager: WebImageContainer::new_texture_manager(),
+                damage: Damage::default(),
             };
             let context = Rc::new(Box::new(rendering_context) as Box<dyn RendererContext>);
-            let game = Game::new(layout);
-            let sender = game.get_sender();
+            let clock = PerformanceClock {
+                performance: window().performance().expect("should have performance on window"),
+            };
+            let game = Game::with_clock(layout, Rc::new(clock));
+            let seed = game.seed();
+            log!("seed {}", seed);
             Self {
                 layout: layout,
                 game: RefCell::new(game),
-                game_sender: sender,
+                seed: Cell::new(seed),
                 rendering_context: context,
             }
         }
@@ -214,16 +508,56 @@ mod wasm {
 
         fn render(&self) {
             let message = ChannelMessage::Render(Rc::clone(&self.rendering_context));
-            self.game_sender.send(message).unwrap();
-            while self.game.borrow_mut().pull() > 0 {}
+            self.game.borrow_mut().send(message);
             self.rendering_context.end_rendering();
         }
 
         pub fn handle_event(&self, event: MouseEventData) {
+            let state = self.game.borrow().board().state();
             let message = ChannelMessage::MouseEvent(event);
-            self.game_sender.send(message).unwrap();
-            while self.game.borrow_mut().pull() > 0 {}
+            self.game.borrow_mut().send(message);
+            self.log_result(state);
+            // let the player know the seed of each new board, so that it can be played again with ?seed=
+            let seed = self.game.borrow().seed();
+            if seed != self.seed.get() {
+                self.seed.set(seed);
+                log!("seed {}", seed);
+            }
         }
+
+        pub fn toggle_heatmap(&self) {
+            self.game.borrow_mut().send(ChannelMessage::ToggleHeatmap);
+        }
+
+        pub fn hint(&self) {
+            let state = self.game.borrow().board().state();
+            self.game.borrow_mut().send(ChannelMessage::Hint);
+            self.log_result(state);
+        }
+
+        // the result is logged when the game is over, with the penalty for the hints
+        fn log_result(&self, before: GameState) {
+            let result = self.game.borrow().result();
+            if result.state != before && matches!(result.state, GameState::Win | GameState::Lose) {
+                log!("{}", result);
+            }
+        }
+    }
+
+    // the seed from the page's query string, e.g. index.html?seed=1234
+    fn seed_from_location() -> Option<u64> {
+        query("seed")?.parse().ok()
+    }
+
+    // a value from the page's query string, e.g. index.html?question-marks=off
+    fn query(name: &str) -> Option<String> {
+        let search = window().location().search().ok()?;
+        let prefix = format!("{}=", name);
+        search
+            .trim_start_matches('?')
+            .split('&')
+            .find_map(|pair| pair.strip_prefix(prefix.as_str()))
+            .map(String::from)
     }
 
 
@@ -247,6 +581,51 @@ mod wasm {
         document().body().expect("document should have a body")
     }
 
+    fn mouse_button(event: &web_sys::MouseEvent) -> MouseButton {
+        match event.button() {
+            0 => MouseButton::Left,
+            2 => MouseButton::Right,
+            _ => MouseButton::Middle,
+        }
+    }
+
+    // the event where it happened on the canvas, it may have been anywhere on the page
+    fn mouse_event(
+        canvas: &web_sys::HtmlCanvasElement,
+        event: &web_sys::MouseEvent,
+        mouse_btn: MouseButton,
+        action: MouseAction,
+    ) -> MouseEventData {
+        let rect = canvas.get_bounding_client_rect();
+        MouseEventData {
+            x: event.client_x() - rect.left() as i32,
+            y: event.client_y() - rect.top() as i32,
+            mouse_btn: mouse_btn,
+            action: action,
+        }
+    }
+
+    // a button under the board, the game is rendered again after it's clicked
+    fn add_button(
+        text: &str,
+        minesweeper: &Rc<Minesweeper>,
+        click: fn(&Minesweeper),
+    ) -> Result<(), JsValue> {
+        let button = document()
+            .create_element("button")?
+            .dyn_into::<web_sys::HtmlElement>()?;
+        button.set_inner_text(text);
+        body().append_child(&button)?;
+        let minesweeper = minesweeper.clone();
+        let closure = Closure::wrap(Box::new(move || {
+            click(&minesweeper);
+            minesweeper.render();
+        }) as Box<dyn FnMut()>);
+        button.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+        Ok(())
+    }
+
     fn make_minesweeper() -> Result<Rc<Minesweeper>, JsValue> {
         // create the canvas and supress default right click
         let canvas = document()
@@ -269,29 +648,58 @@ mod wasm {
         canvas.set_height(minesweeper.height());
         let minesweeper = Rc::new(minesweeper);
         minesweeper.render();
-        // setup the mouse down hook
+        // setup the mouse hooks, pressing left and right together chords just as the middle button does
+        let buttons = Rc::new(Cell::new(MouseButtons::default()));
+        let canvas = Rc::new(canvas);
         {
             let minesweeper = minesweeper.clone();
+            let buttons = buttons.clone();
+            let target = canvas.clone();
             let closure = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
-                let button = match event.button() {
-                    0 => MouseButton::Left,
-                    1 => MouseButton::Middle,
-                    2 => MouseButton::Right,
-                    _ => MouseButton::Middle,
-                };
-                let mouse_event = MouseEventData {
-                    x: event.offset_x(),
-                    y: event.offset_y(),
-                    mouse_btn: button,
-                };
-
-                minesweeper.handle_event(mouse_event);
+                let mut pressed = buttons.get();
+                let mouse_btn = pressed.press(mouse_button(&event));
+                buttons.set(pressed);
+
+                minesweeper.handle_event(mouse_event(&target, &event, mouse_btn, MouseAction::Press));
                 minesweeper.render();
             }) as Box<dyn FnMut(_)>);
             canvas
                 .add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref())?;
             closure.forget();
         }
+        // the mouse can move off the board and the buttons can be let go of anywhere on the page
+        {
+            let minesweeper = minesweeper.clone();
+            let buttons = buttons.clone();
+            let target = canvas.clone();
+            let closure = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
+                if let Some(mouse_btn) = buttons.get().held() {
+                    minesweeper.handle_event(mouse_event(&target, &event, mouse_btn, MouseAction::Move));
+                    minesweeper.render();
+                }
+            }) as Box<dyn FnMut(_)>);
+            document()
+                .add_event_listener_with_callback("mousemove", closure.as_ref().unchecked_ref())?;
+            closure.forget();
+        }
+        {
+            let minesweeper = minesweeper.clone();
+            let target = canvas.clone();
+            let closure = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
+                let mut pressed = buttons.get();
+                let mouse_btn = pressed.release(mouse_button(&event));
+                buttons.set(pressed);
+
+                minesweeper.handle_event(mouse_event(&target, &event, mouse_btn, MouseAction::Release));
+                minesweeper.render();
+            }) as Box<dyn FnMut(_)>);
+            document()
+                .add_event_listener_with_callback("mouseup", closure.as_ref().unchecked_ref())?;
+            closure.forget();
+        }
+        // buttons under the board show the chance of a mine under each hidden tile, and give a hint
+        add_button("Heatmap", &minesweeper, Minesweeper::toggle_heatmap)?;
+        add_button("Hint", &minesweeper, Minesweeper::hint)?;
         // setup request  animation frame loop
         let closure_option = Rc::new(RefCell::new(None));
         let cloned_option = closure_option.clone();
@@ -1,57 +1,206 @@
 extern crate minesweeperlib;
 
-use minesweeperlib::Error;
+use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::time::Duration;
 
-#[cfg(not(any(
-    feature = "media_layer_sdl2",
-    feature = "media_layer_text",
-    feature = "media_layer_wasm"
-)))]
-std::compile_error!("Either feature \"media_layer_sdl2\", feature \"media_layer_text\", or \"media_layer_wasm\" must be enabled for this crate.");
+use minesweeperlib::{Board, Clock, Error, Game, InstantClock, Layout, Options};
+use minesweeperlib::{Replay, ReplayPlayer};
 
-#[cfg(feature = "media_layer_sdl2")]
-#[cfg(any(feature = "media_layer_text", feature = "media_layer_wasm"))]
-std::compile_error!(
-    "Either feature \"media_layer_sdl2\", should not be configured with other media_layers."
-);
+const MEDIA: &str = "--media";
+const BOARD: &str = "--board";
+const RECORD: &str = "--record";
+const REPLAY: &str = "--replay";
+const SPEED: &str = "--speed";
+const WIRING: &str = "--wiring";
+const TRACE: &str = "--trace";
+// the flags for the front ends, rather than the options of the board
+const FLAGS: [&str; 7] = [MEDIA, BOARD, RECORD, REPLAY, SPEED, WIRING, TRACE];
 
-#[cfg(feature = "media_layer_text")]
-#[cfg(any(feature = "media_layer_sdl2", feature = "media_layer_wasm"))]
-std::compile_error!(
-    "Either feature \"media_layer_text\", should not be configured with other media_layers."
-);
+/**
+ * A front end the game can be played with. The binary has one for each media layer it is built with, and the
+ * player picks one with --media, or gets the first.
+ */
+trait FrontEnd {
+    fn name(&self) -> &'static str;
+    fn run(&self, options: Options, board: Option<Board>, replays: Replays) -> Result<(), Error>;
+}
+
+/**
+ * The session's replays, it can be recorded to a file with --record path, and a recording can be played back
+ * instead of the player's input with --replay path, faster or slower with --speed factor. The messages between
+ * the parts of the game can be written to a file too, as JSON lines, with --trace path.
+ */
+struct Replays {
+    record: Option<PathBuf>,
+    player: Option<ReplayPlayer>,
+    trace: Option<PathBuf>,
+    clock: InstantClock,
+}
+
+impl Replays {
+    fn new(args: &[String]) -> Result<Replays, Error> {
+        let value = |name: &str| {
+            args.iter()
+                .position(|arg| arg == name)
+                .map(|index| args.get(index + 1).cloned().unwrap_or_default())
+        };
+        let speed = match value(SPEED) {
+            Some(speed) => match speed.parse::<f64>() {
+                Ok(speed) if speed > 0.0 => speed,
+                _ => return Err(Error::from(format!("{} {:?} isn't valid", SPEED, speed))),
+            },
+            None => 1.0,
+        };
+        let player = match value(REPLAY) {
+            Some(path) => Some(ReplayPlayer::new(Replay::load(path.as_ref())?, speed)),
+            None => None,
+        };
+        Ok(Replays {
+            record: value(RECORD).map(PathBuf::from),
+            player: player,
+            trace: value(TRACE).map(PathBuf::from),
+            clock: InstantClock::new(),
+        })
+    }
+
+    /// The options from the replay, if there is one.
+    fn options(&self) -> Option<Options> {
+        self.player.as_ref().map(|player| player.options())
+    }
+
+    /// Whether the session is being recorded or replayed.
+    fn is_active(&self) -> bool {
+        self.record.is_some() || self.player.is_some()
+    }
+
+    fn is_playing(&self) -> bool {
+        self.player
+            .as_ref()
+            .map_or(false, |player| !player.is_finished())
+    }
+
+    /// How long the front end should wait for input, a replay needs to be looked at often.
+    fn wait(&self) -> Duration {
+        if self.is_playing() {
+            Duration::from_millis(10)
+        } else {
+            Duration::from_secs(1)
+        }
+    }
+
+    /// Start recording the game, and tracing its messages, if there's somewhere to save them.
+    fn start(&self, game: &mut Game) -> Result<(), Error> {
+        if self.record.is_some() {
+            game.record();
+        }
+        if let Some(path) = self.trace.as_ref() {
+            let file = File::create(path)
+                .map_err(|e| Error::from(format!("{}: {}", path.display(), e)))?;
+            game.trace().write_to(Box::new(BufWriter::new(file)));
+        }
+        Ok(())
+    }
 
-#[cfg(feature = "media_layer_wasm")]
-#[cfg(any(feature = "media_layer_sdl2", feature = "media_layer_text"))]
-std::compile_error!(
-    "Either feature \"media_layer_wasm\", should not be configured with other media_layers."
-);
+    /// Play the replay's events which are due, returns whether there were any.
+    fn play(&mut self, game: &mut Game) -> bool {
+        let now = self.clock.now();
+        match self.player.as_mut() {
+            Some(player) => player.play(game, now) > 0,
+            None => false,
+        }
+    }
+
+    /// Save the recording.
+    fn finish(&self, game: &Game) -> Result<(), Error> {
+        match (self.record.as_ref(), game.replay()) {
+            (Some(path), Some(replay)) => {
+                replay.save(path)?;
+                println!("replay saved to {}", path.display());
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
 
 #[cfg(feature = "media_layer_sdl2")]
 mod sdl2_minesweeper {
     use std::cell::RefCell;
+    use std::env;
+    use std::path::{Path, PathBuf};
     use std::rc::Rc;
+    use std::time::Duration;
 
     extern crate sdl2;
-    use sdl2::event::Event;
+    use sdl2::event::{Event, WindowEvent};
     use sdl2::image::InitFlag;
     use sdl2::keyboard::Keycode;
+    use sdl2::messagebox::{ButtonData, ClickedButton, MessageBoxButtonFlag, MessageBoxFlag};
 
     extern crate minesweeperlib;
-    use crate::minesweeperlib::MessageExchange;
-    use crate::minesweeperlib::{ChannelMessage, MouseButton, MouseEvent, MouseHandler};
     use crate::minesweeperlib::{
-        Error, Game, Layout, Rect, Renderer, RendererContext, ResourceContainer, Texture,
-        TextureManager,
+        Board, Color, Damage, Error, Game, GameState, Layout, Options, Rect, RendererContext,
+        SavedGame, Sdl2Container, Texture, TextureManager,
+    };
+    use crate::minesweeperlib::{
+        text_rects, TraceEvent, TraceRecord, DIGITS, GLYPH_ADVANCE, LINE_HEIGHT, TILES,
     };
+    use crate::minesweeperlib::{
+        ChannelMessage, MouseAction, MouseButton, MouseButtons, MouseEventData,
+    };
+    use crate::Replays;
+
+    const SAVE_FILE: &str = ".minesweeper.save";
+    // the messages kept for the overlay, and how many of them it shows
+    const TRACE_KEPT: usize = 200;
+    const OVERLAY_LINES: usize = 12;
+    const RESUME: i32 = 1;
+    const NEW_GAME: i32 = 0;
+
+    fn mouse_button(mouse_btn: sdl2::mouse::MouseButton) -> MouseButton {
+        match mouse_btn {
+            sdl2::mouse::MouseButton::Left => MouseButton::Left,
+            sdl2::mouse::MouseButton::Right => MouseButton::Right,
+            _ => MouseButton::Middle,
+        }
+    }
+
+    fn mouse_event(x: i32, y: i32, mouse_btn: MouseButton, action: MouseAction) -> MouseEventData {
+        MouseEventData {
+            x: x,
+            y: y,
+            mouse_btn: mouse_btn,
+            action: action,
+        }
+    }
+
+    // the game's rects are converted to SDL's at the edge
+    fn sdl_rect(rect: Rect) -> sdl2::rect::Rect {
+        sdl2::rect::Rect::new(rect.left(), rect.top(), rect.width(), rect.height())
+    }
 
     struct RenderingContext {
         pub texture_creator: sdl2::render::TextureCreator<sdl2::video::WindowContext>,
         pub canvas: RefCell<sdl2::render::WindowCanvas>,
+        pub frame: RefCell<sdl2::render::Texture>,
         pub layout: Layout,
         pub texture_manager: TextureManager,
-        pub digits: Vec<String>,
-        pub tiles: Vec<String>,
+        pub damage: Damage,
+        pub overlay: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl RenderingContext {
+        // everything is drawn into the frame, which is kept between renders, as the window's isn't
+        fn draw<F: FnOnce(&mut sdl2::render::WindowCanvas)>(&self, f: F) -> Result<(), String> {
+            let mut frame = self.frame.borrow_mut();
+            let mut canvas = self.canvas.borrow_mut();
+            canvas
+                .with_texture_canvas(&mut frame, f)
+                .map_err(|e| e.to_string())
+        }
     }
 
     impl RendererContext for RenderingContext {
@@ -61,12 +210,9 @@ mod sdl2_minesweeper {
             src: Option<Rect>,
             dst: Rect,
         ) -> Result<(), String> {
-            let taken = texture.get_image_data();
-            match taken {
-                Some(cache) => {
-                    let _result = self.canvas.borrow_mut().copy(&cache.texture, src, dst);
-                    texture.set_image_data(Some(cache));
-                }
+            let (sdl_src, sdl_dst) = (src.map(sdl_rect), sdl_rect(dst));
+            let image = match texture.take_resource::<Sdl2Container>() {
+                Some(cache) => cache,
                 None => match texture.raw_bytes {
                     Some(png) => {
                         let loader = sdl2::rwops::RWops::from_bytes(png)?;
@@ -76,13 +222,30 @@ mod sdl2_minesweeper {
                             .texture_creator
                             .create_texture_from_surface(&surface)
                             .unwrap();
-                        let _result = self.canvas.borrow_mut().copy(&image, src, dst);
-                        texture.set_image_data(Some(ResourceContainer::new(image)));
+                        Sdl2Container::new(image)
                     }
-                    None => {}
+                    None => return Ok(()),
                 },
-            }
-            Ok(())
+            };
+            let result = self.draw(|canvas| {
+                let _result = canvas.copy(&image.texture, sdl_src, sdl_dst);
+            });
+            texture.set_resource(image);
+            self.damage.add(dst);
+            result
+        }
+
+        fn fill_rect(&self, rect: Rect, color: Color) -> Result<(), String> {
+            let mut result = Ok(());
+            self.draw(|canvas| {
+                canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
+                canvas.set_draw_color(sdl2::pixels::Color::RGBA(
+                    color.r, color.g, color.b, color.a,
+                ));
+                result = canvas.fill_rect(sdl_rect(rect));
+            })?;
+            self.damage.add(rect);
+            result
         }
 
         fn layout(&self) -> &Layout {
@@ -94,76 +257,259 @@ mod sdl2_minesweeper {
         }
 
         fn load_digit(&self, value: u64) -> Result<Rc<Texture>, String> {
-            let name = &self.digits[value as usize];
-            let image_name = format!("digit_{}", name);
-            self.load(&image_name)
+            self.load(&format!("digit_{}", DIGITS[value as usize]))
         }
 
         fn load_tile(&self, value: u64) -> Result<Rc<Texture>, String> {
-            let name = &self.tiles[value as usize];
-            let image_name = format!("tile_{}", name);
-            self.load(&image_name)
+            self.load(&format!("tile_{}", TILES[value as usize]))
         }
+
+        fn damage(&self) -> &Damage {
+            &self.damage
+        }
+
+        // the window is only updated when something was drawn
         fn end_rendering(&self) {
-            self.canvas.borrow_mut().present();
+            if self.damage.take().is_empty() {
+                return;
+            }
+            let mut canvas = self.canvas.borrow_mut();
+            let _result = canvas.copy(&self.frame.borrow(), None, None);
+            // the overlay goes over the frame, so the board under it is never drawn over
+            let overlay = self.overlay.borrow();
+            if !overlay.is_empty() {
+                canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
+                canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, 192));
+                let height = overlay.len() as u32 * LINE_HEIGHT + 1;
+                let background = Rect::new(0, 0, self.layout.width(), height);
+                let _result = canvas.fill_rect(sdl_rect(background));
+                canvas.set_draw_color(sdl2::pixels::Color::RGBA(255, 255, 255, 255));
+                for (line, text) in overlay.iter().enumerate() {
+                    let top = 1 + (line as u32 * LINE_HEIGHT) as i32;
+                    for rect in text_rects(text, 1, top, 1) {
+                        let _result = canvas.fill_rect(sdl_rect(rect));
+                    }
+                }
+            }
+            canvas.present();
         }
     }
 
     pub struct Minesweeper {
         pub layout: Layout,
         pub game: Game,
-        game_sender: std::sync::mpsc::Sender<ChannelMessage>,
+        seed: u64,
         rendering_context: Rc<Box<dyn RendererContext>>,
+        overlay: Rc<RefCell<Vec<String>>>,
+        show_overlay: bool,
     }
 
     impl Minesweeper {
-        fn new(canvas: sdl2::render::WindowCanvas) -> Self {
-            let layout = Layout::new();
+        fn new(canvas: sdl2::render::WindowCanvas, layout: Layout) -> Self {
             let texture_creator = canvas.texture_creator();
-            let texture_manager = ResourceContainer::new_texture_manager();
+            let mut frame = texture_creator
+                .create_texture_target(None, layout.width(), layout.height())
+                .unwrap();
+            frame.set_blend_mode(sdl2::render::BlendMode::None);
+            let texture_manager = Sdl2Container::new_texture_manager();
             let canvas = RefCell::new(canvas);
-            let digits = [
-                "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
-            ];
-            let tiles = [
-                "none", "one", "two", "three", "four", "five", "six", "seven", "eight",
-            ];
+            let overlay = Rc::new(RefCell::new(Vec::new()));
             let rendering_context = RenderingContext {
                 texture_creator: texture_creator,
                 canvas: canvas,
+                frame: RefCell::new(frame),
                 layout: layout,
                 texture_manager: texture_manager,
-                digits: digits.iter().map(|s| s.to_string()).collect(),
-                tiles: tiles.iter().map(|s| s.to_string()).collect(),
+                damage: Damage::default(),
+                overlay: overlay.clone(),
             };
             let context = Rc::new(Box::new(rendering_context) as Box<dyn RendererContext>);
             let game = Game::new(layout);
-            let sender = game.get_sender();
+            let seed = game.seed();
+            println!("seed {}", seed);
             Self {
                 layout: layout,
                 game: game,
-                game_sender: sender,
+                seed: seed,
                 rendering_context: context,
+                overlay: overlay,
+                show_overlay: false,
             }
         }
-        
+
         fn render(&mut self) {
             let message = ChannelMessage::Render(Rc::clone(&self.rendering_context));
-            self.game_sender.send(message).unwrap();
-            while self.game.pull() > 0 {}
+            self.game.send(message);
+            if self.show_overlay {
+                self.update_overlay();
+            }
             self.rendering_context.end_rendering();
         }
 
+        // T shows the last messages between the parts of the game over the top of the window
+        fn toggle_overlay(&mut self) {
+            self.show_overlay = !self.show_overlay;
+            let kept = if self.show_overlay { TRACE_KEPT } else { 0 };
+            self.game.trace().keep(kept);
+            self.overlay.borrow_mut().clear();
+            self.expose();
+        }
+
+        // the renders would crowd out everything else, so only the other messages are shown as they arrive
+        fn update_overlay(&mut self) {
+            let records = self.game.trace().records();
+            let received: Vec<&TraceRecord> = records
+                .iter()
+                .filter(|record| record.event == TraceEvent::Received && record.message != "Render")
+                .collect();
+            let columns = (self.layout.width() / GLYPH_ADVANCE) as usize;
+            let lines: Vec<String> = received[received.len().saturating_sub(OVERLAY_LINES)..]
+                .iter()
+                .map(|record| {
+                    let message = record.message.replace("MouseEventData ", "");
+                    format!(
+                        "{} {}>{} {}",
+                        record.sequence, record.sender, record.receiver, message
+                    )
+                    .chars()
+                    .take(columns)
+                    .collect()
+                })
+                .collect();
+            if *self.overlay.borrow() != lines {
+                *self.overlay.borrow_mut() = lines;
+                let window = Rect::new(0, 0, self.layout.width(), self.layout.height());
+                self.rendering_context.damage().add(window);
+            }
+        }
+
+        // the window was uncovered, so it's shown again even though nothing in it changed
+        fn expose(&mut self) {
+            let window = Rect::new(0, 0, self.layout.width(), self.layout.height());
+            self.rendering_context.damage().add(window);
+            self.render();
+        }
+
         fn handle_event(&mut self, event: MouseEventData) {
+            let state = self.game.board().state();
             let message = ChannelMessage::MouseEvent(event);
-            self.game_sender.send(message).unwrap();
-            // since we're not running threads on the channels, perform a complete pull
-            while self.game.pull() > 0 {}
+            self.game.send(message);
+            // let the player know the seed of each new board, so that it can be played again with --seed
+            if self.game.seed() != self.seed {
+                self.seed = self.game.seed();
+                println!("seed {}", self.seed);
+            }
+            self.print_result(state);
+        }
+
+        fn toggle_heatmap(&mut self) {
+            self.game.send(ChannelMessage::ToggleHeatmap);
+        }
+
+        fn hint(&mut self) {
+            let state = self.game.board().state();
+            self.game.send(ChannelMessage::Hint);
+            self.print_result(state);
+        }
+
+        // the result is printed when the game is over, with the penalty for the hints
+        fn print_result(&self, before: GameState) {
+            let result = self.game.result();
+            if result.state != before && matches!(result.state, GameState::Win | GameState::Lose) {
+                println!("{}", result);
+            }
         }
     }
 
-    pub fn main() -> Result<(), Error> {
-        let layout = Rc::new(Layout::new());
+    pub struct Sdl2FrontEnd;
+
+    impl crate::FrontEnd for Sdl2FrontEnd {
+        fn name(&self) -> &'static str {
+            "sdl2"
+        }
+
+        fn run(
+            &self,
+            options: Options,
+            board: Option<Board>,
+            replays: Replays,
+        ) -> Result<(), Error> {
+            main(options, board, replays)
+        }
+    }
+
+    // a game left in progress is saved in the home directory, and offered back the next time
+    fn save_path() -> PathBuf {
+        let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+        home.join(SAVE_FILE)
+    }
+
+    // ask the player whether to carry on with the saved game, if there is one
+    fn resume(path: &Path) -> Option<SavedGame> {
+        let saved = SavedGame::load(path).ok()?;
+        let options = saved.options();
+        let message = format!(
+            "Carry on with the {}x{} game you left, {} seconds in?",
+            options.rows,
+            options.columns,
+            saved.elapsed().as_secs()
+        );
+        let buttons = [
+            ButtonData {
+                flags: MessageBoxButtonFlag::RETURNKEY_DEFAULT,
+                button_id: RESUME,
+                text: "Resume",
+            },
+            ButtonData {
+                flags: MessageBoxButtonFlag::ESCAPEKEY_DEFAULT,
+                button_id: NEW_GAME,
+                text: "New game",
+            },
+        ];
+        let flags = MessageBoxFlag::INFORMATION;
+        match sdl2::messagebox::show_message_box(
+            flags,
+            &buttons,
+            "minesweeper",
+            &message,
+            None,
+            None,
+        ) {
+            Ok(ClickedButton::CustomButton(button)) if button.button_id == RESUME => Some(saved),
+            _ => None,
+        }
+    }
+
+    // save the game if it's still being played, otherwise there's nothing to come back to
+    fn autosave(path: &Path, game: &Game) -> Result<(), Error> {
+        if game.board().state() == GameState::Playing {
+            game.save().save(path)
+        } else {
+            let _ = std::fs::remove_file(path);
+            Ok(())
+        }
+    }
+
+    fn main(options: Options, board: Option<Board>, mut replays: Replays) -> Result<(), Error> {
+        // recordings and replays start from a new board, so they don't save or resume
+        let save_path = if replays.is_active() {
+            None
+        } else {
+            Some(save_path())
+        };
+        // and a board which was asked for is played rather than the saved one
+        let saved = match board {
+            Some(_) => None,
+            None => save_path.as_deref().and_then(resume),
+        };
+        // played with the player's question marks preference, as a save doesn't keep it
+        let options = match &saved {
+            Some(saved) => saved
+                .options()
+                .with_question_marks(options.question_marks()),
+            None => options,
+        };
+        let layout = Layout::new(options);
 
         // init the video subsystem and creat the game window, even in text mode we do this...
         let sdl_context = sdl2::init()?;
@@ -182,48 +528,298 @@ mod sdl2_minesweeper {
             .build()
             .map_err(|e| e.to_string())?;
 
-        let mut minesweeper = Minesweeper::new(canvas);
+        let mut minesweeper = Minesweeper::new(canvas, layout);
+        if let Some(saved) = &saved {
+            minesweeper.game.restore(saved)?;
+        }
+        if let Some(board) = board {
+            minesweeper.game.set_board(board)?;
+        }
+        replays.start(&mut minesweeper.game)?;
         minesweeper.render();
         let mut event_pump: sdl2::EventPump = sdl_context.event_pump()?;
+        let mut buttons = MouseButtons::default();
         'running: loop {
-            match event_pump.wait_event_timeout(100) {
+            let timeout = replays.wait().min(Duration::from_millis(100));
+            match event_pump.wait_event_timeout(timeout.as_millis() as u32) {
                 Some(event) => match event {
                     Event::KeyDown {
                         keycode: Some(Keycode::Escape),
                         ..
                     }
                     | Event::Quit { .. } => break 'running,
+                    Event::Window {
+                        win_event: WindowEvent::Exposed,
+                        ..
+                    } => minesweeper.expose(),
+                    // P shows the chance of a mine under each hidden tile
+                    Event::KeyDown {
+                        keycode: Some(Keycode::P),
+                        ..
+                    } => {
+                        minesweeper.toggle_heatmap();
+                        minesweeper.render();
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::T),
+                        ..
+                    } => minesweeper.toggle_overlay(),
+                    // the player's clicks and hints are ignored while a replay is playing
+                    Event::MouseButtonDown { .. }
+                    | Event::MouseMotion { .. }
+                    | Event::MouseButtonUp { .. }
+                    | Event::KeyDown {
+                        keycode: Some(Keycode::H),
+                        ..
+                    } if replays.is_playing() => {}
+                    // H highlights a safe tile
+                    Event::KeyDown {
+                        keycode: Some(Keycode::H),
+                        ..
+                    } => {
+                        minesweeper.hint();
+                        minesweeper.render();
+                    }
                     Event::MouseButtonDown {
                         x, y, mouse_btn, ..
                     } => {
-                        let mouse_event = MouseEventData {
-                            x: x,
-                            y: y,
-                            mouse_btn: match mouse_btn {
-                                sdl2::mouse::MouseButton::Left => minesweeperlib::MouseButton::Left,
-                                sdl2::mouse::MouseButton::Right => {
-                                    minesweeperlib::MouseButton::Right
-                                }
-                                _ => MouseButton::Middle,
-                            },
-                        };
-                        minesweeper.handle_event(mouse_event);
+                        // pressing left and right together chords, just as the middle button does
+                        let mouse_btn = buttons.press(mouse_button(mouse_btn));
+                        minesweeper.handle_event(mouse_event(x, y, mouse_btn, MouseAction::Press));
+                        minesweeper.render();
+                    }
+                    // the pressed tile follows the mouse while a button is held
+                    Event::MouseMotion { x, y, .. } => {
+                        if let Some(mouse_btn) = buttons.held() {
+                            minesweeper.handle_event(mouse_event(
+                                x,
+                                y,
+                                mouse_btn,
+                                MouseAction::Move,
+                            ));
+                            minesweeper.render();
+                        }
+                    }
+                    Event::MouseButtonUp {
+                        x, y, mouse_btn, ..
+                    } => {
+                        let mouse_btn = buttons.release(mouse_button(mouse_btn));
+                        minesweeper.handle_event(mouse_event(
+                            x,
+                            y,
+                            mouse_btn,
+                            MouseAction::Release,
+                        ));
                         minesweeper.render();
                     }
                     _ => (),
                 },
                 None => {
+                    replays.play(&mut minesweeper.game);
                     minesweeper.render();
                 }
             }
         }
+        if let Some(path) = &save_path {
+            autosave(path, &minesweeper.game)?;
+        }
+        replays.finish(&minesweeper.game)
+    }
+}
+
+#[cfg(feature = "media_layer_text")]
+mod text_minesweeper {
+    use std::io::{stdout, Write};
+    use std::time::{Duration, Instant};
+
+    extern crate crossterm;
+    use crossterm::cursor::MoveTo;
+    use crossterm::event::{poll, read, Event, KeyCode};
+    use crossterm::style::Print;
+    use crossterm::terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+    use crossterm::{execute, queue};
+
+    extern crate minesweeperlib;
+    use crate::minesweeperlib::text::{Command, Minesweeper};
+    use crate::minesweeperlib::{Board, Error, Options};
+    use crate::Replays;
+
+    const HELP: &str = "arrows/hjkl move, space reveals, f flags, c chords, n new game, q quits";
+
+    fn draw(minesweeper: &mut Minesweeper) -> crossterm::Result<()> {
+        let mut out = stdout();
+        let screen = minesweeper.render();
+        queue!(out, Clear(ClearType::All))?;
+        for (line, text) in screen.iter().enumerate() {
+            queue!(out, MoveTo(0, line as u16), Print(text))?;
+        }
+        let status = format!("seed {}", minesweeper.seed());
+        queue!(out, MoveTo(0, screen.len() as u16 + 1), Print(status))?;
+        queue!(out, MoveTo(0, screen.len() as u16 + 2), Print(HELP))?;
+        let (line, column) = minesweeper.cursor();
+        queue!(out, MoveTo(column as u16, line as u16))?;
+        out.flush()?;
         Ok(())
     }
+
+    fn command(code: KeyCode) -> Option<Command> {
+        match code {
+            KeyCode::Up | KeyCode::Char('k') | KeyCode::Char('w') => Some(Command::Up),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('s') => Some(Command::Down),
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('a') => Some(Command::Left),
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('d') => Some(Command::Right),
+            KeyCode::Char(' ') | KeyCode::Enter => Some(Command::Reveal),
+            KeyCode::Char('f') => Some(Command::Flag),
+            KeyCode::Char('c') => Some(Command::Chord),
+            KeyCode::Char('n') => Some(Command::NewGame),
+            _ => None,
+        }
+    }
+
+    fn run(minesweeper: &mut Minesweeper, replays: &mut Replays) -> crossterm::Result<()> {
+        loop {
+            draw(minesweeper)?;
+            // wake up every second to keep the timer moving, or when the replay plays something
+            let redraw = Instant::now() + Duration::from_secs(1);
+            let mut ready = false;
+            while !ready && Instant::now() < redraw {
+                ready = poll(replays.wait())?;
+                if replays.play(minesweeper.game()) {
+                    break;
+                }
+            }
+            if !ready {
+                continue;
+            }
+            if let Event::Key(key) = read()? {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => return Ok(()),
+                    // the player's commands are ignored while a replay is playing
+                    _ if replays.is_playing() => (),
+                    code => {
+                        if let Some(command) = command(code) {
+                            minesweeper.handle_command(command);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub struct TextFrontEnd;
+
+    impl crate::FrontEnd for TextFrontEnd {
+        fn name(&self) -> &'static str {
+            "text"
+        }
+
+        fn run(
+            &self,
+            options: Options,
+            board: Option<Board>,
+            replays: Replays,
+        ) -> Result<(), Error> {
+            main(options, board, replays)
+        }
+    }
+
+    fn main(options: Options, board: Option<Board>, mut replays: Replays) -> Result<(), Error> {
+        let mut minesweeper = Minesweeper::new(options);
+        if let Some(board) = board {
+            minesweeper.game().set_board(board)?;
+        }
+        replays.start(minesweeper.game())?;
+        let terminal = |result: crossterm::Result<()>| result.map_err(|e| e.to_string());
+
+        terminal(crossterm::terminal::enable_raw_mode())?;
+        terminal(execute!(stdout(), EnterAlternateScreen))?;
+        let result = run(&mut minesweeper, &mut replays);
+        // put the terminal back before reporting anything
+        terminal(execute!(stdout(), LeaveAlternateScreen))?;
+        terminal(crossterm::terminal::disable_raw_mode())?;
+        terminal(result)?;
+        println!("seed {}", minesweeper.seed());
+        replays.finish(minesweeper.game())
+    }
 }
 
-fn main() -> Result<(), Error> {
+fn front_ends() -> Vec<Box<dyn FrontEnd>> {
+    let mut front_ends: Vec<Box<dyn FrontEnd>> = Vec::new();
     #[cfg(feature = "media_layer_sdl2")]
-    let _result = sdl2_minesweeper::main();
+    front_ends.push(Box::new(sdl2_minesweeper::Sdl2FrontEnd));
+    #[cfg(feature = "media_layer_text")]
+    front_ends.push(Box::new(text_minesweeper::TextFrontEnd));
+    front_ends
+}
+
+// a board written as text, which is played instead of one placed from a seed
+fn load_board(path: Option<&String>) -> Result<Board, Error> {
+    let path = path.map(String::as_str).unwrap_or_default();
+    let text =
+        std::fs::read_to_string(path).map_err(|e| Error::from(format!("{}: {}", path, e)))?;
+    text.parse()
+}
 
-    Ok(())
+fn run() -> Result<(), Error> {
+    let args: Vec<String> = env::args().collect();
+    let asked = Options::new_with_flags(args.clone(), &FLAGS)?;
+    let replays = Replays::new(&args)?;
+    let board = match args.iter().position(|arg| arg == BOARD) {
+        Some(index) => Some(load_board(args.get(index + 1))?),
+        None => None,
+    };
+    // a replay places its own boards
+    if board.is_some() && replays.is_active() {
+        let message = format!("a board from {} can't be recorded or replayed", BOARD);
+        return Err(Error::from(message));
+    }
+    let options = match (&board, replays.options()) {
+        // a board from a file is played with the player's question marks preference
+        (Some(board), _) => board.options().with_question_marks(asked.question_marks()),
+        (None, Some(options)) => options,
+        (None, None) => asked,
+    };
+    // draw how the parts of the game send messages to each other, rather than playing
+    if let Some(index) = args.iter().position(|arg| arg == WIRING) {
+        let path = args.get(index + 1).map(String::as_str).unwrap_or_default();
+        Game::new(Layout::new(options))
+            .wiring()
+            .save_dot(path.as_ref())?;
+        println!("wiring saved to {}", path);
+        return Ok(());
+    }
+    let front_ends = front_ends();
+    let front_end = match args.iter().position(|arg| arg == MEDIA) {
+        Some(index) => {
+            let name = args.get(index + 1).map(String::as_str).unwrap_or_default();
+            let front_end = front_ends.iter().find(|front_end| front_end.name() == name);
+            if front_end.is_none() {
+                let names: Vec<&str> = front_ends
+                    .iter()
+                    .map(|front_end| front_end.name())
+                    .collect();
+                let message = format!(
+                    "unknown front end {:?}, it should be one of {:?}",
+                    name, names
+                );
+                return Err(Error::from(message));
+            }
+            front_end
+        }
+        None => front_ends.first(),
+    };
+    match front_end {
+        Some(front_end) => front_end.run(options, board, replays),
+        None => Err(Error::from(
+            "there isn't a front end, build with feature media_layer_sdl2 or media_layer_text"
+                .to_string(),
+        )),
+    }
+}
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
 }
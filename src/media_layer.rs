@@ -1,3 +1,4 @@
+use std::any::Any;
 use std::borrow::Borrow;
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
@@ -132,18 +133,46 @@ mod tests {
     }
 }
 
+mod glyphs;
+pub use self::glyphs::{text_rects, GLYPH_ADVANCE, LINE_HEIGHT};
+mod headless;
+pub use self::headless::HeadlessContext;
+mod snapshot;
+pub use self::snapshot::{Frame, SnapshotContext};
 #[cfg(feature = "media_layer_sdl2")]
 mod sdl2;
 #[cfg(feature = "media_layer_sdl2")]
-pub type ResourceContainer = self::sdl2::Sdl2Container;
+pub use self::sdl2::Sdl2Container;
 #[cfg(feature = "media_layer_text")]
 mod text;
 #[cfg(feature = "media_layer_text")]
-pub type ResourceContainer = self::text::TextContainer;
+pub use self::text::TextContainer;
 #[cfg(feature = "media_layer_wasm")]
 mod wasm;
 #[cfg(feature = "media_layer_wasm")]
-pub type ResourceContainer = self::wasm::WebImageContainer;
+pub use self::wasm::WebImageContainer;
 
-pub type Texture = ImageContainer<ResourceContainer>;
+/// The image data in a texture belongs to the media layer which drew it, any number of them can be built in.
+pub type Texture = ImageContainer<Box<dyn Any>>;
 pub type TextureManager = ResourceManager<String, Texture, AssetLoader>;
+
+/// The names of the digit and tile images by their value, e.g. digit_three and tile_none.
+pub const DIGITS: [&str; 10] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+pub const TILES: [&str; 9] = [
+    "none", "one", "two", "three", "four", "five", "six", "seven", "eight",
+];
+
+impl Texture {
+    /// Take the image data out of the texture, provided it is the media layer's own container.
+    pub fn take_resource<R: Any>(&self) -> Option<R> {
+        let image_data = self.image_data.take()?;
+        image_data.downcast::<R>().ok().map(|resource| *resource)
+    }
+
+    /// Put the media layer's container into the texture, to be taken the next time it is drawn.
+    pub fn set_resource<R: Any>(&self, resource: R) {
+        self.image_data.set(Some(Box::new(resource)));
+    }
+}
@@ -0,0 +1,121 @@
+use crate::sprites::Rect;
+
+/// The width and height of a glyph, and how far apart they are, in pixels before scaling.
+pub const GLYPH_WIDTH: u32 = 3;
+pub const GLYPH_HEIGHT: u32 = 5;
+pub const GLYPH_ADVANCE: u32 = GLYPH_WIDTH + 1;
+pub const LINE_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+
+// each row of a glyph, top first, with the left pixel in the highest of the three bits
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '{' => [0b011, 0b010, 0b110, 0b010, 0b011],
+        '}' => [0b110, 0b010, 0b011, 0b010, 0b110],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '?' => [0b110, 0b001, 0b010, 0b000, 0b010],
+        _ => [0; 5],
+    }
+}
+
+/**
+ * The rects to fill to draw a line of text in a small blocky font, for the front ends which have no way of drawing
+ * text of their own. Each row of each glyph is a rect for each run of pixels, scaled up by scale. Letters are all
+ * drawn as capitals, and the characters without a glyph are left blank.
+ */
+pub fn text_rects(text: &str, left: i32, top: i32, scale: u32) -> Vec<Rect> {
+    let mut rects = Vec::new();
+    for (position, c) in text.chars().enumerate() {
+        let x = left + (position as u32 * GLYPH_ADVANCE * scale) as i32;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            let y = top + (row as u32 * scale) as i32;
+            let mut column = 0;
+            while column < GLYPH_WIDTH {
+                let lit = |column: u32| bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0;
+                if !lit(column) {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < GLYPH_WIDTH && lit(column) {
+                    column += 1;
+                }
+                let (width, height) = ((column - start) * scale, scale);
+                rects.push(Rect::new(x + (start * scale) as i32, y, width, height));
+            }
+        }
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::text_rects;
+    use crate::sprites::Rect;
+
+    #[test]
+    fn test_text_rects() {
+        // the runs of each row of the T, then the 1 one glyph along
+        assert_eq!(
+            text_rects("T1", 10, 20, 1),
+            vec![
+                Rect::new(10, 20, 3, 1),
+                Rect::new(11, 21, 1, 1),
+                Rect::new(11, 22, 1, 1),
+                Rect::new(11, 23, 1, 1),
+                Rect::new(11, 24, 1, 1),
+                Rect::new(15, 20, 1, 1),
+                Rect::new(14, 21, 2, 1),
+                Rect::new(15, 22, 1, 1),
+                Rect::new(15, 23, 1, 1),
+                Rect::new(14, 24, 3, 1),
+            ]
+        );
+        // scaled up, and a space is nothing
+        assert_eq!(text_rects("- ", 0, 0, 2), vec![Rect::new(0, 4, 6, 2)]);
+        assert_eq!(text_rects("t", 0, 0, 1), text_rects("T", 0, 0, 1));
+    }
+}
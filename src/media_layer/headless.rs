@@ -0,0 +1,68 @@
+use std::rc::Rc;
+
+use super::{AssetLoader, DIGITS, TILES};
+use super::{Texture, TextureManager};
+use crate::config::Layout;
+use crate::sprites::{Color, Damage, Rect, RendererContext};
+
+/**
+ * The HeadlessContext is a RendererContext which doesn't draw anything. It is always built in, so the game can
+ * be rendered by tests and tools without a display. The images are still loaded, so a missing one is an error,
+ * and what would have been drawn is still added to the damage.
+ */
+pub struct HeadlessContext {
+    layout: Layout,
+    texture_manager: TextureManager,
+    damage: Damage,
+}
+
+impl HeadlessContext {
+    pub fn new(layout: Layout) -> Self {
+        Self {
+            layout: layout,
+            texture_manager: TextureManager::new(AssetLoader {}),
+            damage: Damage::default(),
+        }
+    }
+}
+
+impl RendererContext for HeadlessContext {
+    fn render_image(
+        &self,
+        _texture: &Texture,
+        _src: Option<Rect>,
+        dst: Rect,
+    ) -> Result<(), String> {
+        self.damage.add(dst);
+        Ok(())
+    }
+
+    fn fill_rect(&self, rect: Rect, _color: Color) -> Result<(), String> {
+        self.damage.add(rect);
+        Ok(())
+    }
+
+    fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    fn load(&self, name: &str) -> Result<Rc<Texture>, String> {
+        self.texture_manager.load(name)
+    }
+
+    fn load_digit(&self, value: u64) -> Result<Rc<Texture>, String> {
+        self.load(&format!("digit_{}", DIGITS[value as usize]))
+    }
+
+    fn load_tile(&self, value: u64) -> Result<Rc<Texture>, String> {
+        self.load(&format!("tile_{}", TILES[value as usize]))
+    }
+
+    fn damage(&self) -> &Damage {
+        &self.damage
+    }
+
+    fn end_rendering(&self) {
+        self.damage.take();
+    }
+}
@@ -0,0 +1,287 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use super::{AssetLoader, DIGITS, TILES};
+use super::{Texture, TextureManager};
+use crate::config::Layout;
+use crate::sprites::{Color, Damage, Rect, RendererContext};
+
+/**
+ * Frame is an RGBA image in memory, four bytes a pixel, a row at a time.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Frame {
+    /// Create a frame with every pixel transparent black.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width: width,
+            height: height,
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    /// Decode a PNG, which should be 8 bits per channel RGB or RGBA.
+    pub fn from_png(bytes: &[u8]) -> Result<Self, String> {
+        let decoder = png::Decoder::new(bytes);
+        let (info, mut reader) = decoder.read_info().map_err(|e| e.to_string())?;
+        let mut data = vec![0; info.buffer_size()];
+        reader.next_frame(&mut data).map_err(|e| e.to_string())?;
+        let pixels = match (info.color_type, info.bit_depth) {
+            (png::ColorType::RGBA, png::BitDepth::Eight) => data,
+            (png::ColorType::RGB, png::BitDepth::Eight) => data
+                .chunks(3)
+                .flat_map(|rgb| vec![rgb[0], rgb[1], rgb[2], 255])
+                .collect(),
+            (color_type, bit_depth) => {
+                return Err(format!("unsupported png, {:?} {:?}", color_type, bit_depth))
+            }
+        };
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels: pixels,
+        })
+    }
+
+    /// Encode the frame as an RGBA PNG.
+    pub fn to_png(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+            encoder.set_color(png::ColorType::RGBA);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+            writer
+                .write_image_data(&self.pixels)
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(bytes)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_png()?).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let offset = self.offset(x, y);
+        let rgba = &self.pixels[offset..offset + 4];
+        Color {
+            r: rgba[0],
+            g: rgba[1],
+            b: rgba[2],
+            a: rgba[3],
+        }
+    }
+
+    /// The number of pixels which aren't the same in both frames, every pixel when the sizes differ.
+    pub fn differences(&self, other: &Frame) -> usize {
+        if (self.width, self.height) != (other.width, other.height) {
+            return std::cmp::max(self.pixels.len(), other.pixels.len()) / 4;
+        }
+        self.pixels
+            .chunks(4)
+            .zip(other.pixels.chunks(4))
+            .filter(|(a, b)| a != b)
+            .count()
+    }
+
+    fn offset(&self, x: u32, y: u32) -> usize {
+        ((y * self.width + x) * 4) as usize
+    }
+
+    // blend a color over the pixel at x, y
+    fn blend(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return;
+        }
+        let offset = self.offset(x as u32, y as u32);
+        let alpha = color.a as u32;
+        let over =
+            |src: u8, dst: u8| ((src as u32 * alpha + dst as u32 * (255 - alpha)) / 255) as u8;
+        let pixel = &mut self.pixels[offset..offset + 4];
+        pixel[0] = over(color.r, pixel[0]);
+        pixel[1] = over(color.g, pixel[1]);
+        pixel[2] = over(color.b, pixel[2]);
+        pixel[3] = (alpha + pixel[3] as u32 * (255 - alpha) / 255) as u8;
+    }
+
+    // the average of the pixels in the source rect, which always has at least one pixel
+    fn average(&self, left: u32, top: u32, right: u32, bottom: u32) -> Color {
+        let (right, bottom) = (right.max(left + 1), bottom.max(top + 1));
+        let mut sum = [0u32; 4];
+        for y in top..bottom {
+            for x in left..right {
+                let offset = self.offset(x, y);
+                for (total, value) in sum.iter_mut().zip(&self.pixels[offset..offset + 4]) {
+                    *total += *value as u32;
+                }
+            }
+        }
+        let count = (right - left) * (bottom - top);
+        Color {
+            r: (sum[0] / count) as u8,
+            g: (sum[1] / count) as u8,
+            b: (sum[2] / count) as u8,
+            a: (sum[3] / count) as u8,
+        }
+    }
+
+    // draw the src part of image into dst, scaling it to fit by averaging the pixels which land on each pixel
+    fn draw(&mut self, image: &Frame, src: Rect, dst: Rect) {
+        let (src_width, src_height) = (src.width() as u64, src.height() as u64);
+        let (dst_width, dst_height) = (dst.width() as u64, dst.height() as u64);
+        let scale = |start: i32, position: u64, from: u64, to: u64| {
+            (start as u64 + position * from / to) as u32
+        };
+        for y in 0..dst_height {
+            let top = scale(src.top(), y, src_height, dst_height);
+            let bottom = scale(src.top(), y + 1, src_height, dst_height);
+            for x in 0..dst_width {
+                let left = scale(src.left(), x, src_width, dst_width);
+                let right = scale(src.left(), x + 1, src_width, dst_width);
+                let color = image.average(left, top, right, bottom);
+                self.blend(dst.left() + x as i32, dst.top() + y as i32, color);
+            }
+        }
+    }
+}
+
+/**
+ * The SnapshotContext is a software RendererContext. It composites the images into a Frame in memory, which
+ * can be compared or saved as a PNG, so the rendering can be tested without a display.
+ */
+pub struct SnapshotContext {
+    layout: Layout,
+    texture_manager: TextureManager,
+    frame: Rc<RefCell<Frame>>,
+    damage: Damage,
+}
+
+impl SnapshotContext {
+    pub fn new(layout: Layout) -> Self {
+        Self {
+            layout: layout,
+            texture_manager: TextureManager::new(AssetLoader {}),
+            frame: Rc::new(RefCell::new(Frame::new(layout.width(), layout.height()))),
+            damage: Damage::default(),
+        }
+    }
+
+    /// The frame being drawn into. It is shared, so it can still be looked at once the context is boxed up.
+    pub fn frame(&self) -> Rc<RefCell<Frame>> {
+        self.frame.clone()
+    }
+}
+
+impl RendererContext for SnapshotContext {
+    fn render_image(&self, texture: &Texture, src: Option<Rect>, dst: Rect) -> Result<(), String> {
+        let image = match texture.take_resource::<Frame>() {
+            Some(image) => image,
+            None => match texture.raw_bytes() {
+                Some(png) => Frame::from_png(png)?,
+                None => return Err("unable to load png from memory".to_string()),
+            },
+        };
+        let src = src.unwrap_or_else(|| Rect::new(0, 0, image.width(), image.height()));
+        self.frame.borrow_mut().draw(&image, src, dst);
+        texture.set_resource(image);
+        self.damage.add(dst);
+        Ok(())
+    }
+
+    fn fill_rect(&self, rect: Rect, color: Color) -> Result<(), String> {
+        let mut frame = self.frame.borrow_mut();
+        for y in rect.top()..rect.bottom() {
+            for x in rect.left()..rect.right() {
+                frame.blend(x, y, color);
+            }
+        }
+        self.damage.add(rect);
+        Ok(())
+    }
+
+    fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    fn load(&self, name: &str) -> Result<Rc<Texture>, String> {
+        self.texture_manager.load(name)
+    }
+
+    fn load_digit(&self, value: u64) -> Result<Rc<Texture>, String> {
+        self.load(&format!("digit_{}", DIGITS[value as usize]))
+    }
+
+    fn load_tile(&self, value: u64) -> Result<Rc<Texture>, String> {
+        self.load(&format!("tile_{}", TILES[value as usize]))
+    }
+
+    fn damage(&self) -> &Damage {
+        &self.damage
+    }
+
+    fn end_rendering(&self) {
+        self.damage.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Frame, SnapshotContext};
+    use crate::config::{Layout, BEGINNER_OPTIONS};
+    use crate::sprites::{Color, Rect, RendererContext};
+
+    #[test]
+    fn test_png() {
+        let mut frame = Frame::new(3, 2);
+        frame.blend(1, 1, Color::rgb(10, 20, 30));
+        let copy = Frame::from_png(&frame.to_png().unwrap()).unwrap();
+        assert_eq!(copy, frame);
+        assert_eq!(copy.pixel(1, 1), Color::rgb(10, 20, 30));
+        assert_eq!(copy.pixel(0, 1).a, 0);
+        assert_eq!(Frame::new(3, 2).differences(&frame), 1);
+        assert_eq!(Frame::new(2, 3).differences(&frame), 6);
+        assert!(Frame::from_png(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let context = SnapshotContext::new(Layout::new(BEGINNER_OPTIONS));
+        let frame = context.frame();
+        assert_eq!(
+            (frame.borrow().width(), frame.borrow().height()),
+            (210, 276)
+        );
+
+        context
+            .fill_rect(Rect::new(-5, -5, 10, 10), Color::rgb(255, 0, 0))
+            .unwrap();
+        assert_eq!(frame.borrow().pixel(4, 4), Color::rgb(255, 0, 0));
+        assert_eq!(frame.borrow().pixel(5, 5).a, 0);
+
+        // the tile image is scaled down to the tile
+        let tile = context.load("tile").unwrap();
+        let dst = Rect::new(15, 81, 20, 20);
+        context.render_image(&tile, None, dst).unwrap();
+        assert!(tile.has_image_data());
+        let frame = frame.borrow();
+        assert_eq!(frame.pixel(15, 81).a, 255);
+        assert_eq!(frame.pixel(35, 101).a, 0);
+        assert!(context.load("missing").is_err());
+    }
+}
@@ -1,10 +1,45 @@
 use super::AssetLoader;
 use super::TextureManager;
 
-pub struct TextContainer {}
+/**
+ * The TextContainer carries the characters an image is drawn with in a terminal. Images without a glyph,
+ * like the digit panel, aren't drawn.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TextContainer {
+    pub glyph: &'static str,
+}
 
 impl TextContainer {
-    pub fn new_texture_manager() -> TextureManager<Self> {
+    pub fn new(name: &str) -> Self {
+        let glyph = match name {
+            "tile" => ".",
+            "tile_flag" => "F",
+            "tile_question" => "?",
+            "tile_mine" => "X",
+            "tile_mine_unexploded" => "*",
+            "tile_flag_wrong" => "x",
+            "tile_none" => " ",
+            "tile_one" | "digit_one" => "1",
+            "tile_two" | "digit_two" => "2",
+            "tile_three" | "digit_three" => "3",
+            "tile_four" | "digit_four" => "4",
+            "tile_five" | "digit_five" => "5",
+            "tile_six" | "digit_six" => "6",
+            "tile_seven" | "digit_seven" => "7",
+            "tile_eight" | "digit_eight" => "8",
+            "digit_nine" => "9",
+            "digit_zero" => "0",
+            "face_playing" => ":)",
+            "face_surprised" => ":o",
+            "face_win" => "B)",
+            "face_lose" => "X(",
+            _ => "",
+        };
+        Self { glyph }
+    }
+
+    pub fn new_texture_manager() -> TextureManager {
         let creator = AssetLoader {};
         TextureManager::new(creator)
     }
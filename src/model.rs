@@ -0,0 +1,5 @@
+mod board;
+pub use board::{Board, CellView, GameState};
+
+mod minefield;
+pub use minefield::Minefield;
@@ -0,0 +1,798 @@
+use snafu::ensure;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::Options;
+use crate::model::Minefield;
+use crate::sprites::{Error, InvalidBoard};
+
+// common enums
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GameState {
+    Init,
+    Playing,
+    Win,
+    Lose,
+}
+
+impl GameState {
+    /// The name used for it in saved games.
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameState::Init => "init",
+            GameState::Playing => "playing",
+            GameState::Win => "win",
+            GameState::Lose => "lose",
+        }
+    }
+}
+
+impl FromStr for GameState {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<GameState, ()> {
+        match name {
+            "init" => Ok(GameState::Init),
+            "playing" => Ok(GameState::Playing),
+            "win" => Ok(GameState::Win),
+            "lose" => Ok(GameState::Lose),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The state of a single cell, as the player has left it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Cell {
+    Hidden,
+    Flagged,
+    Questioned,
+    Revealed,
+}
+
+/// What a cell looks like to the player. This is all that is needed to render a cell.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CellView {
+    Hidden,
+    Flagged,
+    Questioned,   //< Hidden, with a question mark which doesn't count as a flag
+    Revealed(u8), //< Revealed, with the number of adjacent mines
+    Mine,         //< Revealed, and it was a mine
+    HiddenMine,   //< Hidden, and a mine, shown once the game is lost
+    WrongFlag,    //< Flagged, but not a mine, shown once the game is lost
+}
+
+impl CellView {
+    /// Whether the cell can be revealed, it's hidden with or without a question mark.
+    pub fn is_hidden(&self) -> bool {
+        matches!(self, CellView::Hidden | CellView::Questioned)
+    }
+}
+
+/**
+ * Board is the game model. It owns the minefield, the state of every cell and the state of the game, and
+ * applies the rules when the player reveals, flags or chords. It has no knowledge of rendering or of the
+ * channel machinery, the sprites render from it, and bots, tests and tools can play a game with it directly.
+ */
+pub struct Board {
+    options: Options,
+    minefield: Minefield,
+    cells: Vec<Cell>,
+    state: GameState,
+    revealed: i32,
+    flags: i32,
+    hints: u32,
+}
+
+impl Board {
+    /// Create a board from the seed in the options, or a random seed if there isn't one.
+    pub fn new(options: Options) -> Self {
+        let seed = options.seed().unwrap_or_else(random_seed);
+        Board::with_minefield(options, Minefield::new(options, seed))
+    }
+
+    /// Create a board with the mines at the given indices, rather than from a seed.
+    pub fn with_mines(options: Options, mines: &[u32]) -> Self {
+        Board::with_minefield(options, Minefield::with_mines(options, mines))
+    }
+
+    /**
+     * Create a board as it was saved. The cells are the views of them, which have to agree with the mines, and
+     * the state of the game follows from them. The mines are None when they hadn't been placed yet.
+     */
+    pub fn restore(
+        options: Options,
+        seed: u64,
+        mines: Option<&[u32]>,
+        cells: &[CellView],
+    ) -> Result<Board, Error> {
+        let tiles = options.tiles();
+        ensure!(
+            cells.len() == tiles as usize,
+            InvalidBoard {
+                reason: format!("it has {} tiles, the board has {}", cells.len(), tiles)
+            }
+        );
+        if let Some(mines) = mines {
+            let mut sorted = mines.to_vec();
+            sorted.sort_unstable();
+            sorted.dedup();
+            ensure!(
+                sorted.len() == options.mines() as usize
+                    && sorted.iter().all(|index| (*index as i32) < tiles),
+                InvalidBoard {
+                    reason: format!(
+                        "it has mines at {:?}, the board has {}",
+                        mines,
+                        options.mines()
+                    )
+                }
+            );
+        }
+
+        let mut board = Board::with_minefield(options, Minefield::restore(options, seed, mines));
+        for (index, view) in cells.iter().enumerate() {
+            board.cells[index] = match view {
+                CellView::Hidden | CellView::HiddenMine => Cell::Hidden,
+                CellView::Flagged | CellView::WrongFlag => Cell::Flagged,
+                CellView::Questioned => Cell::Questioned,
+                CellView::Revealed(_) | CellView::Mine => Cell::Revealed,
+            };
+            ensure!(
+                board.cell_at(index as u32) == *view
+                    && (mines.is_some() || !board.is_revealed(index)),
+                InvalidBoard {
+                    reason: format!("tile {} doesn't agree with the mines", index)
+                }
+            );
+        }
+        board.flags = board.count(Cell::Flagged);
+        ensure!(
+            board.flags <= options.mines(),
+            InvalidBoard {
+                reason: format!("it has {} flags, more than the mines", board.flags)
+            }
+        );
+        let exploded = (0..tiles as u32).any(|index| board.cell_at(index) == CellView::Mine);
+        board.revealed = board.count(Cell::Revealed) - exploded as i32;
+        board.state = if exploded {
+            GameState::Lose
+        } else if board.revealed == options.blanks() {
+            GameState::Win
+        } else if board.revealed > 0 {
+            GameState::Playing
+        } else {
+            GameState::Init
+        };
+        Ok(board)
+    }
+
+    fn with_minefield(options: Options, minefield: Minefield) -> Self {
+        Self {
+            options: options,
+            minefield: minefield,
+            cells: vec![Cell::Hidden; options.tiles() as usize],
+            state: GameState::Init,
+            revealed: 0,
+            flags: 0,
+            hints: 0,
+        }
+    }
+
+    /// Start a new game with a random seed, the mines are placed again on the first reveal.
+    pub fn reset(&mut self) {
+        self.reset_with_seed(random_seed());
+    }
+
+    /// Start a new game with the given seed, the mines are placed again on the first reveal.
+    pub fn reset_with_seed(&mut self, seed: u64) {
+        self.minefield.reset(seed);
+        for cell in self.cells.iter_mut() {
+            *cell = Cell::Hidden;
+        }
+        self.state = GameState::Init;
+        self.revealed = 0;
+        self.flags = 0;
+        self.hints = 0;
+    }
+
+    /// The indices of the mines, None until they're placed on the first reveal.
+    pub fn mines(&self) -> Option<Vec<u32>> {
+        self.minefield.mines()
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// The seed the mines are placed from. Together with the first reveal it determines where the mines are.
+    pub fn seed(&self) -> u64 {
+        self.minefield.seed()
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn flags(&self) -> i32 {
+        self.flags
+    }
+
+    pub fn flags_remaining(&self) -> i32 {
+        self.options.mines() - self.flags
+    }
+
+    /// The number of hints the player has been given on this board.
+    pub fn hints(&self) -> u32 {
+        self.hints
+    }
+
+    /// Count a hint given to the player, each one is a penalty on the result.
+    pub fn add_hint(&mut self) {
+        self.hints += 1;
+    }
+
+    /// Get the board with the number of hints given on it, for a board which is restored.
+    pub fn with_hints(mut self, hints: u32) -> Board {
+        self.hints = hints;
+        self
+    }
+
+    /// Get the board with question marks turned on or off, as they're the player's preference and not the board's.
+    pub fn with_question_marks(mut self, question_marks: bool) -> Board {
+        self.options = self.options.with_question_marks(question_marks);
+        self
+    }
+
+    pub fn revealed(&self) -> i32 {
+        self.revealed
+    }
+
+    /// Get the view of the cell at row, column. Cells outside of the board are hidden.
+    pub fn cell(&self, row: i16, column: i16) -> CellView {
+        match self.index_of(row, column) {
+            Some(index) => self.cell_at(index),
+            None => CellView::Hidden,
+        }
+    }
+
+    /// Get the view of the cell at index.
+    pub fn cell_at(&self, index: u32) -> CellView {
+        match self.cells[index as usize] {
+            Cell::Hidden => CellView::Hidden,
+            Cell::Flagged => CellView::Flagged,
+            Cell::Questioned => CellView::Questioned,
+            Cell::Revealed if self.minefield.mine_at(index) => CellView::Mine,
+            Cell::Revealed => CellView::Revealed(self.minefield.adjacent_mines(index)),
+        }
+    }
+
+    /**
+     * Get what the tile at index shows. It's the view of the cell until the game is lost, then the mines which
+     * weren't found are shown, and the flags which weren't on a mine. Once the game is won the mines are all
+     * flagged, so there's nothing more to show.
+     */
+    pub fn shown_at(&self, index: u32) -> CellView {
+        let view = self.cell_at(index);
+        if self.state != GameState::Lose {
+            return view;
+        }
+        match (view, self.minefield.mine_at(index)) {
+            (CellView::Hidden, true) | (CellView::Questioned, true) => CellView::HiddenMine,
+            (CellView::Flagged, false) => CellView::WrongFlag,
+            (view, _) => view,
+        }
+    }
+
+    /// Reveal the cell at row, column. Revealing a cell without adjacent mines reveals its neighbors.
+    pub fn reveal(&mut self, row: i16, column: i16) {
+        if let Some(index) = self.index_of(row, column) {
+            self.reveal_from(index);
+        }
+    }
+
+    /// Flag or unflag the cell at row, column. There can't be more flags than mines.
+    pub fn toggle_flag(&mut self, row: i16, column: i16) {
+        let index = match self.index_of(row, column) {
+            Some(index) => index as usize,
+            None => return,
+        };
+        if !self.is_playing() {
+            return;
+        }
+        match self.cells[index] {
+            Cell::Hidden | Cell::Questioned if self.flags < self.options.mines() => {
+                self.cells[index] = Cell::Flagged;
+                self.flags += 1;
+            }
+            Cell::Flagged => {
+                self.cells[index] = Cell::Hidden;
+                self.flags -= 1;
+            }
+            _ => (),
+        }
+    }
+
+    /**
+     * Mark the cell at row, column as a right click does, from hidden to flagged, then to a question mark if
+     * they're turned on in the options, and back to hidden. When there aren't any flags left a hidden cell goes
+     * straight to a question mark.
+     */
+    pub fn mark(&mut self, row: i16, column: i16) {
+        let index = match self.index_of(row, column) {
+            Some(index) => index as usize,
+            None => return,
+        };
+        if !self.is_playing() {
+            return;
+        }
+        let question_marks = self.options.question_marks();
+        match self.cells[index] {
+            Cell::Hidden if self.flags < self.options.mines() => self.toggle_flag(row, column),
+            Cell::Hidden if question_marks => self.cells[index] = Cell::Questioned,
+            Cell::Flagged => {
+                self.toggle_flag(row, column);
+                if question_marks {
+                    self.cells[index] = Cell::Questioned;
+                }
+            }
+            Cell::Questioned => self.cells[index] = Cell::Hidden,
+            _ => (),
+        }
+    }
+
+    /// Reveal the unflagged neighbors of a revealed cell, provided it has as many adjacent flags as mines.
+    pub fn chord(&mut self, row: i16, column: i16) {
+        let index = match self.index_of(row, column) {
+            Some(index) => index,
+            None => return,
+        };
+        if self.cells[index as usize] != Cell::Revealed {
+            return;
+        }
+        let mut neighbors: Vec<u32> = Vec::new();
+        let options = self.options;
+        options.for_each_neighbor(index, |row, column| {
+            neighbors.push(options.index(row, column))
+        });
+        let adjacent_flags = neighbors
+            .iter()
+            .filter(|neighbor| self.cells[**neighbor as usize] == Cell::Flagged)
+            .count();
+        if adjacent_flags == self.minefield.adjacent_mines(index) as usize {
+            for neighbor in neighbors {
+                self.reveal_from(neighbor);
+            }
+        }
+    }
+
+    // once the game is won, every mine which isn't flagged yet is
+    fn flag_mines(&mut self) {
+        for index in 0..self.cells.len() {
+            if self.cells[index] != Cell::Revealed {
+                self.cells[index] = Cell::Flagged;
+            }
+        }
+        self.flags = self.options.mines();
+    }
+
+    fn is_revealed(&self, index: usize) -> bool {
+        self.cells[index] == Cell::Revealed
+    }
+
+    fn count(&self, state: Cell) -> i32 {
+        self.cells.iter().filter(|cell| **cell == state).count() as i32
+    }
+
+    fn is_playing(&self) -> bool {
+        self.state == GameState::Init || self.state == GameState::Playing
+    }
+
+    fn index_of(&self, row: i16, column: i16) -> Option<u32> {
+        if row >= 0 && row < self.options.rows && column >= 0 && column < self.options.columns {
+            Some(self.options.index(row, column))
+        } else {
+            None
+        }
+    }
+
+    // reveal a cell, and cascade through any neighbors which don't have adjacent mines
+    fn reveal_from(&mut self, index: u32) {
+        let options = self.options;
+        let mut pending = vec![index];
+        while let Some(index) = pending.pop() {
+            let hidden = matches!(self.cells[index as usize], Cell::Hidden | Cell::Questioned);
+            if !self.is_playing() || !hidden {
+                continue;
+            }
+            if !self.minefield.is_placed() {
+                self.minefield.place_mines(index);
+            }
+            self.cells[index as usize] = Cell::Revealed;
+            if self.minefield.mine_at(index) {
+                self.state = GameState::Lose;
+                continue;
+            }
+            self.state = GameState::Playing;
+            self.revealed += 1;
+            if self.revealed == options.blanks() {
+                self.state = GameState::Win;
+                self.flag_mines();
+            } else if self.minefield.adjacent_mines(index) == 0 {
+                options.for_each_neighbor(index, |row, column| {
+                    pending.push(options.index(row, column))
+                });
+            }
+        }
+    }
+}
+
+/**
+ * A board is written as text a row to a line, with a character for each cell and where the mines are:
+ *
+ * .  hidden               *  hidden mine
+ * F  flagged mine         f  flagged, but not a mine
+ * Q  question mark, mine  q  question mark, but not a mine
+ * 0-8  revealed, with the number of adjacent mines
+ * X  revealed mine
+ *
+ * Blank lines and lines starting with # are skipped when reading, so a board can be described. Until the first
+ * reveal places the mines there aren't any to write.
+ */
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mines: BTreeSet<u32> = self.mines().unwrap_or_default().into_iter().collect();
+        for row in 0..self.options.rows {
+            let line: String = (0..self.options.columns)
+                .map(|column| {
+                    let index = self.options.index(row, column);
+                    match (self.cell_at(index), mines.contains(&index)) {
+                        (CellView::Hidden, false) => '.',
+                        (CellView::Hidden, true) => '*',
+                        (CellView::Flagged, true) => 'F',
+                        (CellView::Flagged, false) => 'f',
+                        (CellView::Questioned, true) => 'Q',
+                        (CellView::Questioned, false) => 'q',
+                        (CellView::Revealed(count), _) => (b'0' + count) as char,
+                        (CellView::Mine, _) => 'X',
+                        (CellView::HiddenMine, _) => '*',
+                        (CellView::WrongFlag, _) => 'f',
+                    }
+                })
+                .collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Board {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Board, Error> {
+        let mut rows = 0;
+        let mut mines = Vec::new();
+        let mut cells = Vec::new();
+        let lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()));
+        for (number, line) in lines.filter(|(_, line)| !line.is_empty() && !line.starts_with('#')) {
+            for c in line.chars() {
+                let (mine, cell) = match c {
+                    '.' => (false, CellView::Hidden),
+                    '*' => (true, CellView::Hidden),
+                    'F' => (true, CellView::Flagged),
+                    'f' => (false, CellView::Flagged),
+                    'Q' => (true, CellView::Questioned),
+                    'q' => (false, CellView::Questioned),
+                    '0'..='8' => (false, CellView::Revealed(c as u8 - b'0')),
+                    'X' => (true, CellView::Mine),
+                    _ => {
+                        return InvalidBoard {
+                            reason: format!("line {} has {:?}", number, c),
+                        }
+                        .fail()
+                    }
+                };
+                if mine {
+                    mines.push(cells.len() as u32);
+                }
+                cells.push(cell);
+            }
+            rows += 1;
+            ensure!(
+                cells.len() % rows == 0 && cells.len() / rows == line.chars().count(),
+                InvalidBoard {
+                    reason: format!("line {} isn't as long as the first", number)
+                }
+            );
+        }
+        ensure!(
+            rows > 0,
+            InvalidBoard {
+                reason: "there aren't any rows".to_string()
+            }
+        );
+        let columns = cells.len() / rows;
+        let options = Options::custom(rows as i16, columns as i16, mines.len() as i32)?;
+        Board::restore(options, 0, Some(&mines), &cells)
+    }
+}
+
+fn random_seed() -> u64 {
+    rand::random()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Board, CellView, GameState};
+    use crate::config::{FirstClick, BEGINNER_OPTIONS, EXPERT_OPTIONS};
+
+    // mines around the top left corner and along the bottom of the right edge
+    fn board() -> Board {
+        Board::with_mines(BEGINNER_OPTIONS, &[0, 2, 10, 26, 35, 44, 53, 62, 71, 80])
+    }
+
+    #[test]
+    fn test_cascade() {
+        let mut board = board();
+        assert_eq!(board.state(), GameState::Init);
+        board.reveal(4, 3);
+        assert_eq!(board.state(), GameState::Playing);
+        assert_eq!(board.cell(4, 3), CellView::Revealed(0));
+        assert_eq!(board.cell(2, 0), CellView::Revealed(1));
+        assert_eq!(board.cell(4, 7), CellView::Revealed(3));
+        assert_eq!(board.cell(0, 8), CellView::Revealed(0));
+        assert_eq!(board.cell(0, 0), CellView::Hidden);
+        assert_eq!(board.cell(0, 1), CellView::Hidden);
+        assert_eq!(board.cell(1, 0), CellView::Hidden);
+        assert_eq!(board.revealed(), 69);
+    }
+
+    #[test]
+    fn test_win() {
+        let mut board = board();
+        board.reveal(4, 3);
+        board.reveal(0, 1);
+        assert_eq!(board.state(), GameState::Playing);
+        board.reveal(1, 0);
+        assert_eq!(board.cell(1, 0), CellView::Revealed(2));
+        assert_eq!(board.state(), GameState::Win);
+        // the mines left are flagged, and the game is over so nothing changes
+        assert_eq!(board.cell(0, 0), CellView::Flagged);
+        assert_eq!(board.flags_remaining(), 0);
+        board.reveal(0, 0);
+        assert_eq!(board.cell(0, 0), CellView::Flagged);
+    }
+
+    #[test]
+    fn test_lose() {
+        let mut board = board();
+        board.reveal(4, 3);
+        board.reveal(0, 0);
+        assert_eq!(board.state(), GameState::Lose);
+        assert_eq!(board.cell(0, 0), CellView::Mine);
+        board.toggle_flag(0, 1);
+        assert_eq!(board.cell(0, 1), CellView::Hidden);
+    }
+
+    #[test]
+    fn test_shown() {
+        let mut board = board();
+        board.reveal(4, 3);
+        board.toggle_flag(0, 2);
+        board.toggle_flag(0, 1);
+        assert_eq!(board.shown_at(1), CellView::Flagged);
+        assert_eq!(board.shown_at(10), CellView::Hidden);
+        // once it's lost, the other mines and the wrong flags are shown as well
+        board.reveal(0, 0);
+        assert_eq!(board.shown_at(0), CellView::Mine);
+        assert_eq!(board.shown_at(1), CellView::WrongFlag);
+        assert_eq!(board.shown_at(2), CellView::Flagged);
+        assert_eq!(board.shown_at(10), CellView::HiddenMine);
+        assert_eq!(board.shown_at(9), CellView::Hidden);
+        assert_eq!(board.cell_at(10), CellView::Hidden);
+    }
+
+    #[test]
+    fn test_flags() {
+        let mut board = board();
+        board.toggle_flag(0, 0);
+        assert_eq!(board.cell(0, 0), CellView::Flagged);
+        assert_eq!(board.flags_remaining(), 9);
+        // flagged cells can't be revealed
+        board.reveal(0, 0);
+        assert_eq!(board.cell(0, 0), CellView::Flagged);
+        board.toggle_flag(0, 0);
+        assert_eq!(board.cell(0, 0), CellView::Hidden);
+        assert_eq!(board.flags_remaining(), 10);
+        // there can't be more flags than mines
+        for column in 0..9 {
+            board.toggle_flag(4, column);
+            board.toggle_flag(5, column);
+        }
+        assert_eq!(board.flags(), 10);
+        assert_eq!(board.cell(5, 5), CellView::Hidden);
+    }
+
+    #[test]
+    fn test_question_marks() {
+        let mut board = board();
+        board.mark(0, 0);
+        assert_eq!(board.cell(0, 0), CellView::Flagged);
+        board.mark(0, 0);
+        assert_eq!(board.cell(0, 0), CellView::Questioned);
+        // a question mark isn't a flag, so it isn't enough to chord, and it can be revealed
+        assert_eq!(board.flags(), 0);
+        board.mark(1, 1);
+        board.mark(1, 1);
+        board.reveal(2, 2);
+        board.chord(2, 2);
+        assert_eq!(board.cell(1, 2), CellView::Hidden);
+        board.reveal(1, 1);
+        assert_eq!(board.state(), GameState::Lose);
+        assert_eq!(board.cell(1, 1), CellView::Mine);
+
+        // with them turned off, a flag is hidden again
+        let mines = board.mines().unwrap();
+        let options = board.options().with_question_marks(false);
+        let mut board = Board::with_mines(options, &mines);
+        board.mark(0, 0);
+        board.mark(0, 0);
+        assert_eq!(board.cell(0, 0), CellView::Hidden);
+        board.mark(0, 0);
+        assert_eq!(board.cell(0, 0), CellView::Flagged);
+    }
+
+    #[test]
+    fn test_first_click() {
+        let mut board = Board::new(EXPERT_OPTIONS.with_first_click(FirstClick::Opening));
+        for _ in 0..100 {
+            board.reset();
+            board.reveal(7, 15);
+            assert_eq!(board.cell(7, 15), CellView::Revealed(0));
+            assert_ne!(board.state(), GameState::Lose);
+        }
+        // flagging before the first reveal is fine, the opening is still mine free
+        board.reset();
+        board.toggle_flag(0, 0);
+        board.reveal(0, 1);
+        assert_eq!(board.cell(0, 1), CellView::Revealed(0));
+    }
+
+    #[test]
+    fn test_seed() {
+        let options = EXPERT_OPTIONS.with_seed(7);
+        let mut first = Board::new(options);
+        let mut second = Board::new(options);
+        assert_eq!(first.seed(), 7);
+        first.reveal(8, 8);
+        second.reveal(8, 8);
+        for index in 0..options.tiles() as u32 {
+            assert_eq!(first.cell_at(index), second.cell_at(index));
+        }
+
+        first.reset();
+        let seed = first.seed();
+        second.reset_with_seed(seed);
+        first.reveal(0, 0);
+        second.reveal(0, 0);
+        for index in 0..options.tiles() as u32 {
+            assert_eq!(first.cell_at(index), second.cell_at(index));
+        }
+    }
+
+    #[test]
+    fn test_chord() {
+        let mut board = board();
+        board.reveal(2, 2);
+        assert_eq!(board.cell(2, 2), CellView::Revealed(1));
+        // not enough flags
+        board.chord(2, 2);
+        assert_eq!(board.cell(1, 2), CellView::Hidden);
+        board.toggle_flag(1, 1);
+        board.chord(2, 2);
+        assert_eq!(board.cell(1, 2), CellView::Revealed(2));
+        assert_eq!(board.cell(2, 3), CellView::Revealed(0));
+        assert_eq!(board.cell(1, 1), CellView::Flagged);
+        assert_eq!(board.state(), GameState::Playing);
+    }
+
+    #[test]
+    fn test_chord_wrong_flag() {
+        let mut board = board();
+        board.reveal(2, 2);
+        board.toggle_flag(1, 2);
+        board.chord(2, 2);
+        assert_eq!(board.state(), GameState::Lose);
+        assert_eq!(board.cell(1, 1), CellView::Mine);
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut board = board();
+        board.reveal(4, 3);
+        board.toggle_flag(0, 0);
+        let options = *board.options();
+        let cells: Vec<CellView> = (0..options.tiles() as u32)
+            .map(|index| board.cell_at(index))
+            .collect();
+        let mines = board.mines().unwrap();
+        let restored = Board::restore(options, 0, Some(&mines), &cells).unwrap();
+        assert_eq!(restored.state(), GameState::Playing);
+        assert_eq!(restored.revealed(), board.revealed());
+        assert_eq!(restored.flags(), 1);
+        assert_eq!(restored.cell(4, 7), CellView::Revealed(3));
+
+        // the views have to agree with the mines
+        let mut wrong = cells.clone();
+        wrong[0] = CellView::Revealed(1);
+        assert!(Board::restore(options, 0, Some(&mines), &wrong).is_err());
+        assert!(Board::restore(options, 0, Some(&mines[1..]), &cells).is_err());
+        assert!(Board::restore(options, 0, None, &cells).is_err());
+        assert!(Board::restore(options, 0, Some(&mines), &cells[1..]).is_err());
+
+        // a board which hasn't been revealed doesn't have mines yet
+        let restored = Board::restore(options, 5, None, &[CellView::Hidden; 81]).unwrap();
+        assert_eq!(restored.state(), GameState::Init);
+        assert_eq!(restored.seed(), 5);
+
+        board.reveal(0, 2);
+        let cells: Vec<CellView> = (0..options.tiles() as u32)
+            .map(|index| board.cell_at(index))
+            .collect();
+        let restored = Board::restore(options, 0, Some(&mines), &cells).unwrap();
+        assert_eq!(restored.state(), GameState::Lose);
+    }
+
+    #[test]
+    fn test_text() {
+        let mut board: Board = "\
+            # mines in a corner and along the bottom\n\
+            *.....\n\
+            ......\n\
+            ......\n\
+            .**...\n"
+            .parse()
+            .unwrap();
+        assert_eq!((board.options().rows, board.options().columns), (4, 6));
+        assert_eq!(board.options().mines(), 3);
+        assert_eq!(board.state(), GameState::Init);
+        board.reveal(0, 5);
+        board.toggle_flag(0, 0);
+        board.toggle_flag(3, 0);
+        board.mark(3, 2);
+        board.mark(3, 2);
+        assert_eq!(board.to_string(), "F10000\n.10000\n.22100\nf*Q100\n");
+        let mut copy: Board = board.to_string().parse().unwrap();
+        assert_eq!(copy.to_string(), board.to_string());
+        assert_eq!(copy.state(), GameState::Playing);
+        assert_eq!(copy.flags(), 2);
+
+        // chording with a wrong flag loses
+        copy.toggle_flag(3, 1);
+        copy.chord(2, 1);
+        assert_eq!(copy.state(), GameState::Lose);
+        assert_eq!(copy.to_string(), "F10000\n110000\n122100\nfFX100\n");
+
+        let error = |text: &str| match text.parse::<Board>() {
+            Ok(_) => String::new(),
+            Err(error) => error.to_string(),
+        };
+        assert_eq!(error("..\n.?\n"), "the board isn't valid, line 2 has '?'");
+        assert_eq!(
+            error("..\n...\n"),
+            "the board isn't valid, line 2 isn't as long as the first"
+        );
+        assert_eq!(
+            error("# nothing\n"),
+            "the board isn't valid, there aren't any rows"
+        );
+        assert_eq!(
+            error("*2\n"),
+            "the board isn't valid, tile 1 doesn't agree with the mines"
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::BTreeSet;
+
+use crate::config::{FirstClick, Options};
+use crate::model::{Board, CellView, GameState};
+use crate::solver::play_safe_moves;
+
+// how many layouts a no guess board tries, and how many times each is repaired, before settling for the last one
+const NO_GUESS_LAYOUTS: usize = 20;
+const NO_GUESS_REPAIRS: usize = 50;
+
+/**
+ * Minefield holds the location of the mines, and nothing else. It knows nothing about what the player has
+ * revealed or flagged, that is the job of the Board.
+ *
+ * The mines aren't placed until the first reveal, so that the first click protection in the Options can
+ * keep them away from it. The placement is generated from a seed, the same seed and first reveal always
+ * place the same mines.
+ *
+ * A no guess board is played out from the first reveal with the solver. When it gets stuck, the mines it couldn't
+ * find are moved away from what has been revealed and the board is played again, and if that doesn't get
+ * anywhere the mines are placed again. A board too crowded to be solved at all is left as the last try.
+ */
+pub struct Minefield {
+    options: Options,
+    seed: u64,
+    mines: BTreeSet<u32>,
+    placed: bool,
+}
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+impl Minefield {
+    pub fn new(options: Options, seed: u64) -> Self {
+        Self {
+            options: options,
+            seed: seed,
+            mines: BTreeSet::new(),
+            placed: false,
+        }
+    }
+
+    /// Create a minefield with the mines at the given indices, rather than from a seed. Its seed is 0.
+    pub fn with_mines(options: Options, mines: &[u32]) -> Self {
+        Self {
+            options: options,
+            seed: 0,
+            mines: mines.iter().cloned().collect(),
+            placed: true,
+        }
+    }
+
+    /// Create a minefield as it was saved, with the mines when they had been placed.
+    pub fn restore(options: Options, seed: u64, mines: Option<&[u32]>) -> Self {
+        let mut minefield = Minefield::new(options, seed);
+        if let Some(mines) = mines {
+            minefield.mines = mines.iter().cloned().collect();
+            minefield.placed = true;
+        }
+        minefield
+    }
+
+    /// The indices of the mines, None until they're placed.
+    pub fn mines(&self) -> Option<Vec<u32>> {
+        if self.placed {
+            Some(self.mines.iter().cloned().collect())
+        } else {
+            None
+        }
+    }
+
+    pub fn is_placed(&self) -> bool {
+        self.placed
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn mine_at(&self, index: u32) -> bool {
+        self.mines.contains(&index)
+    }
+
+    pub fn adjacent_mines(&self, index: u32) -> u8 {
+        let mut sum = 0;
+        let closure = |row, column| {
+            let index = self.options.index(row, column);
+            if self.mine_at(index) {
+                sum += 1;
+            }
+        };
+        self.options.for_each_neighbor(index, closure);
+        sum
+    }
+
+    /// Clear the mines, they'll be placed from the new seed on the next first reveal.
+    pub fn reset(&mut self, seed: u64) {
+        self.seed = seed;
+        self.mines.clear();
+        self.placed = false;
+    }
+
+    /// Place the mines, keeping them away from the first reveal as the options require.
+    pub fn place_mines(&mut self, first_reveal: u32) {
+        let max_index = self.options.tiles();
+        let mine_count = self.options.mines() as usize;
+        let excluded = self.excluded(first_reveal);
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+
+        let layouts = match self.options.first_click() {
+            FirstClick::NoGuess => NO_GUESS_LAYOUTS,
+            _ => 1,
+        };
+        for _ in 0..layouts {
+            self.mines.clear();
+            while self.mines.len() < mine_count {
+                // a board which fits in an i16 is dealt as it always was, so that its seed gives the same board
+                let index = if max_index <= i16::MAX as i32 {
+                    rng.gen_range(0, max_index as i16) as u32
+                } else {
+                    rng.gen_range(0, max_index as u32)
+                };
+                if !excluded.contains(&index) {
+                    self.mines.insert(index);
+                }
+            }
+            if layouts == 1 || self.repair(first_reveal, &excluded, &mut rng) {
+                break;
+            }
+        }
+        self.placed = true;
+    }
+
+    // move the mines until the board can be solved from the first reveal without guessing, or give up
+    fn repair(
+        &mut self,
+        first_reveal: u32,
+        excluded: &BTreeSet<u32>,
+        rng: &mut ChaCha8Rng,
+    ) -> bool {
+        let options = self.options;
+        let (row, column) = options.row_column(first_reveal);
+        for _ in 0..NO_GUESS_REPAIRS {
+            let mines: Vec<u32> = self.mines.iter().cloned().collect();
+            let mut board = Board::with_mines(options, &mines);
+            board.reveal(row, column);
+            play_safe_moves(&mut board);
+            if board.state() == GameState::Win {
+                return true;
+            }
+
+            // the mines next to the numbers, where the solver got stuck, and the hidden cells away from the
+            // numbers, which the mines can be moved to without being in the way
+            let next_to_revealed = |index: u32| {
+                let mut next = false;
+                options.for_each_neighbor(index, |row, column| {
+                    next |= matches!(board.cell(row, column), CellView::Revealed(_));
+                });
+                next
+            };
+            let (mut stuck, mut away) = (Vec::new(), Vec::new());
+            for index in 0..options.tiles() as u32 {
+                if board.cell_at(index) != CellView::Hidden || excluded.contains(&index) {
+                    continue;
+                }
+                match (self.mines.contains(&index), next_to_revealed(index)) {
+                    (true, true) => stuck.push(index),
+                    (false, false) => away.push(index),
+                    _ => (),
+                }
+            }
+            if stuck.is_empty() || away.is_empty() {
+                return false;
+            }
+            stuck.shuffle(rng);
+            away.shuffle(rng);
+            for (from, to) in stuck.into_iter().zip(away) {
+                self.mines.remove(&from);
+                self.mines.insert(to);
+            }
+        }
+        false
+    }
+
+    // the indices which must be mine free, falling back to fewer when there isn't room for all of the mines
+    fn excluded(&self, first_reveal: u32) -> BTreeSet<u32> {
+        let mut excluded = BTreeSet::new();
+        if self.options.first_click() == FirstClick::Any {
+            return excluded;
+        }
+        excluded.insert(first_reveal);
+        if let FirstClick::Opening | FirstClick::NoGuess = self.options.first_click() {
+            let closure = |row, column| {
+                excluded.insert(self.options.index(row, column));
+            };
+            self.options.for_each_neighbor(first_reveal, closure);
+        }
+        if self.options.tiles() - (excluded.len() as i32) < self.options.mines() {
+            excluded.clear();
+            if self.options.blanks() > 0 {
+                excluded.insert(first_reveal);
+            }
+        }
+        excluded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Minefield;
+    use crate::config::{FirstClick, Options, BEGINNER_OPTIONS, INTERMEDIATE_OPTIONS};
+    use crate::model::{Board, GameState};
+    use crate::solver::play_safe_moves;
+
+    fn count_mines(minefield: &Minefield, options: &Options) -> usize {
+        (0..options.tiles() as u32)
+            .filter(|index| minefield.mine_at(*index))
+            .count()
+    }
+
+    #[test]
+    fn test_placement() {
+        let mut minefield = Minefield::new(BEGINNER_OPTIONS, 1);
+        assert!(!minefield.is_placed());
+        assert_eq!(count_mines(&minefield, &BEGINNER_OPTIONS), 0);
+        minefield.place_mines(40);
+        assert!(minefield.is_placed());
+        assert_eq!(count_mines(&minefield, &BEGINNER_OPTIONS), 10);
+        minefield.reset(2);
+        assert!(!minefield.is_placed());
+        assert_eq!(count_mines(&minefield, &BEGINNER_OPTIONS), 0);
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut minefield = Minefield::new(BEGINNER_OPTIONS, 1);
+        assert_eq!(minefield.mines(), None);
+        minefield.place_mines(40);
+        let mines = minefield.mines().unwrap();
+        assert_eq!(mines.len(), 10);
+        let restored = Minefield::restore(BEGINNER_OPTIONS, 1, Some(&mines));
+        assert!(restored.is_placed());
+        assert_eq!(restored.mines(), Some(mines));
+        assert!(!Minefield::restore(BEGINNER_OPTIONS, 1, None).is_placed());
+    }
+
+    #[test]
+    fn test_seed() {
+        let mines = |seed, first_reveal| {
+            let mut minefield = Minefield::new(BEGINNER_OPTIONS, seed);
+            minefield.place_mines(first_reveal);
+            (0..BEGINNER_OPTIONS.tiles() as u32)
+                .filter(|index| minefield.mine_at(*index))
+                .collect::<Vec<u32>>()
+        };
+        assert_eq!(mines(42, 40), mines(42, 40));
+        assert_ne!(mines(42, 40), mines(43, 40));
+    }
+
+    #[test]
+    fn test_first_click() {
+        for seed in 0..100 {
+            let options = BEGINNER_OPTIONS.with_first_click(FirstClick::Safe);
+            let mut minefield = Minefield::new(options, seed);
+            minefield.place_mines(40);
+            assert!(!minefield.mine_at(40));
+            assert_eq!(count_mines(&minefield, &options), 10);
+
+            let options = BEGINNER_OPTIONS.with_first_click(FirstClick::Opening);
+            let mut minefield = Minefield::new(options, seed);
+            minefield.place_mines(0);
+            assert!(!minefield.mine_at(0));
+            assert_eq!(minefield.adjacent_mines(0), 0);
+            assert_eq!(count_mines(&minefield, &options), 10);
+        }
+    }
+
+    #[test]
+    fn test_no_guess() {
+        // every board can be played out from the first reveal, which opens an area
+        for options in [BEGINNER_OPTIONS, INTERMEDIATE_OPTIONS].iter() {
+            let options = options.with_first_click(FirstClick::NoGuess);
+            let (row, column) = (options.rows / 2, options.columns / 2);
+            for seed in 0..10 {
+                let mut minefield = Minefield::new(options, seed);
+                let first_reveal = options.index(row, column);
+                minefield.place_mines(first_reveal);
+                assert_eq!(count_mines(&minefield, &options), options.mines() as usize);
+                assert_eq!(minefield.adjacent_mines(first_reveal), 0);
+
+                let mut board = Board::with_mines(options, &minefield.mines().unwrap());
+                board.reveal(row, column);
+                play_safe_moves(&mut board);
+                assert_eq!(board.state(), GameState::Win);
+            }
+        }
+    }
+
+    #[test]
+    fn test_adjacent_mines() {
+        let minefield = Minefield::with_mines(BEGINNER_OPTIONS, &[0, 1, 9]);
+        assert_eq!(minefield.adjacent_mines(10), 3);
+        assert_eq!(minefield.adjacent_mines(0), 2);
+        assert_eq!(minefield.adjacent_mines(2), 1);
+        assert_eq!(minefield.adjacent_mines(80), 0);
+    }
+}
@@ -0,0 +1,7 @@
+mod constraint;
+
+mod probability;
+pub use probability::Probabilities;
+
+mod solver;
+pub use solver::{play_safe_moves, Solution, Solver};
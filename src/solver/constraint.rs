@@ -0,0 +1,190 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/**
+ * Constraint is what a revealed number says about its hidden neighbors: exactly mines of the cells are mines.
+ * The cells are indices into the board.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Constraint {
+    pub cells: BTreeSet<u32>,
+    pub mines: i16,
+}
+
+impl Constraint {
+    /// All of the cells are safe.
+    pub fn is_safe(&self) -> bool {
+        self.mines == 0
+    }
+
+    /// All of the cells are mines.
+    pub fn is_mines(&self) -> bool {
+        self.mines == self.cells.len() as i16
+    }
+}
+
+/// Group the constraints which share cells, directly or through each other. Each group can be solved on its own.
+pub fn components(constraints: &[Constraint]) -> Vec<Vec<&Constraint>> {
+    let mut by_cell: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+    for (index, constraint) in constraints.iter().enumerate() {
+        for cell in constraint.cells.iter() {
+            by_cell.entry(*cell).or_default().push(index);
+        }
+    }
+
+    let mut seen = vec![false; constraints.len()];
+    let mut components = Vec::new();
+    for start in 0..constraints.len() {
+        if seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let mut pending = vec![start];
+        let mut component = Vec::new();
+        while let Some(index) = pending.pop() {
+            component.push(&constraints[index]);
+            for cell in constraints[index].cells.iter() {
+                for other in by_cell[cell].iter() {
+                    if !seen[*other] {
+                        seen[*other] = true;
+                        pending.push(*other);
+                    }
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
+
+/// The cells of the constraints, each once, with the cells of a constraint kept together so that the search
+/// finds contradictions early.
+pub fn cells(constraints: &[&Constraint]) -> Vec<u32> {
+    let mut seen = BTreeSet::new();
+    let mut cells = Vec::new();
+    for constraint in constraints.iter() {
+        for cell in constraint.cells.iter() {
+            if seen.insert(*cell) {
+                cells.push(*cell);
+            }
+        }
+    }
+    cells
+}
+
+/**
+ * Visit every way of placing mines in the cells which satisfies all of the constraints, the assignment has
+ * true for a mine in the order of the cells. It's a backtracking search, which stops going down a branch as
+ * soon as a constraint can't be met, but it is still exponential so the caller should bound the cells.
+ */
+pub fn enumerate(cells: &[u32], constraints: &[&Constraint], visit: &mut dyn FnMut(&[bool])) {
+    let position: BTreeMap<u32, usize> = cells
+        .iter()
+        .enumerate()
+        .map(|(position, cell)| (*cell, position))
+        .collect();
+    // the constraints as positions, and the constraints on each position
+    let constraints: Vec<(Vec<usize>, i16)> = constraints
+        .iter()
+        .map(|constraint| {
+            let positions = constraint.cells.iter().map(|cell| position[cell]).collect();
+            (positions, constraint.mines)
+        })
+        .collect();
+    let mut by_position = vec![Vec::new(); cells.len()];
+    for (index, (positions, _)) in constraints.iter().enumerate() {
+        for position in positions.iter() {
+            by_position[*position].push(index);
+        }
+    }
+
+    let mut search = Search {
+        constraints: constraints,
+        by_position: by_position,
+        assignment: vec![false; cells.len()],
+    };
+    search.next(0, visit);
+}
+
+struct Search {
+    constraints: Vec<(Vec<usize>, i16)>,
+    by_position: Vec<Vec<usize>>,
+    assignment: Vec<bool>,
+}
+
+impl Search {
+    fn next(&mut self, position: usize, visit: &mut dyn FnMut(&[bool])) {
+        if position == self.assignment.len() {
+            visit(&self.assignment);
+            return;
+        }
+        for mine in [false, true].iter() {
+            self.assignment[position] = *mine;
+            if self.is_possible(position) {
+                self.next(position + 1, visit);
+            }
+        }
+        self.assignment[position] = false;
+    }
+
+    // whether the constraints on the cell can still be met, with the cells up to it assigned
+    fn is_possible(&self, position: usize) -> bool {
+        self.by_position[position].iter().all(|index| {
+            let (positions, mines) = &self.constraints[*index];
+            let assigned = positions.iter().filter(|p| **p <= position);
+            let placed = assigned.clone().filter(|p| self.assignment[**p]).count() as i16;
+            let open = (positions.len() - assigned.count()) as i16;
+            placed <= *mines && placed + open >= *mines
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cells, components, enumerate, Constraint};
+
+    fn constraint(cells: &[u32], mines: i16) -> Constraint {
+        Constraint {
+            cells: cells.iter().cloned().collect(),
+            mines: mines,
+        }
+    }
+
+    #[test]
+    fn test_components() {
+        let constraints = [
+            constraint(&[1, 2], 1),
+            constraint(&[7, 8], 1),
+            constraint(&[2, 3], 1),
+            constraint(&[3, 4], 1),
+        ];
+        let components = components(&constraints);
+        assert_eq!(components.len(), 2);
+        assert_eq!(cells(&components[0]), vec![1, 2, 3, 4]);
+        assert_eq!(cells(&components[1]), vec![7, 8]);
+    }
+
+    #[test]
+    fn test_enumerate() {
+        // these only have one answer
+        let constraints = [
+            constraint(&[0, 1], 1),
+            constraint(&[0, 1, 2], 2),
+            constraint(&[1, 2, 3], 2),
+            constraint(&[2, 3], 1),
+        ];
+        let constraints: Vec<&Constraint> = constraints.iter().collect();
+        let cells = cells(&constraints);
+        let mut solutions = Vec::new();
+        enumerate(&cells, &constraints, &mut |assignment| {
+            solutions.push(assignment.to_vec())
+        });
+        assert_eq!(solutions, vec![vec![false, true, true, false]]);
+
+        // and a 1 on its own has two
+        let constraints = [constraint(&[5, 6], 1)];
+        let constraints: Vec<&Constraint> = constraints.iter().collect();
+        let mut count = 0;
+        enumerate(&[5, 6], &constraints, &mut |_| count += 1);
+        assert_eq!(count, 2);
+    }
+}
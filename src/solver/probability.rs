@@ -0,0 +1,552 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use crate::config::Options;
+use crate::model::{Board, CellView};
+use crate::solver::constraint::{cells, components, Constraint};
+use crate::solver::Solver;
+
+/**
+ * Probabilities is the chance that each cell is a mine, counted exactly over every way the mines could be placed
+ * which agrees with the revealed numbers and the number of mines on the board.
+ *
+ * Each group of numbers which share cells is counted by going along its cells one at a time, keeping the ways
+ * for each state of the numbers part way through rather than trying every placement, so the time grows with how
+ * wide the edge of the revealed area is rather than doubling with each cell along it.
+ *
+ * Flags are taken to be right, as the FlagCounter takes them, so flagged cells are mines and the mines left to
+ * place are the ones it shows. When the flags can't be right the probabilities are worked out without them.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probabilities {
+    probabilities: Vec<Option<f64>>,
+}
+
+impl Probabilities {
+    /// Work out the probabilities for the cells, as the player sees them, of a board with the options.
+    pub fn new(options: Options, cells: &[CellView]) -> Self {
+        let flagged: Vec<CellView> = cells
+            .iter()
+            .map(|cell| match cell {
+                CellView::Flagged => CellView::Mine,
+                cell => *cell,
+            })
+            .collect();
+        let probabilities = count(options, &flagged).or_else(|| count(options, cells));
+        let probabilities = probabilities.unwrap_or_else(|| vec![None; cells.len()]);
+        // the revealed cells are what the probabilities are worked out from, they don't have one
+        let probabilities = probabilities
+            .into_iter()
+            .zip(cells)
+            .map(|(probability, cell)| match cell {
+                CellView::Revealed(_) | CellView::Mine => None,
+                _ => probability,
+            })
+            .collect();
+        Self {
+            probabilities: probabilities,
+        }
+    }
+
+    /// Work out the probabilities for the board as the player sees it.
+    pub fn from_board(board: &Board) -> Self {
+        let options = *board.options();
+        let cells: Vec<CellView> = (0..options.tiles() as u32)
+            .map(|index| board.cell_at(index))
+            .collect();
+        Probabilities::new(options, &cells)
+    }
+
+    /// The chance that the cell is a mine, there isn't one for revealed cells or a board which doesn't add up.
+    pub fn get(&self, index: u32) -> Option<f64> {
+        self.probabilities[index as usize]
+    }
+}
+
+// the number of ways a group of constraints can be met with each number of mines, and how many of those have a
+// mine in each cell. Rather than the counts, they're kept in proportion, as they can be far too big for a float.
+struct Counts {
+    cells: Vec<u32>,
+    ways: Vec<f64>,
+    mines: Vec<Vec<f64>>,
+}
+
+// the ways of placing mines in the cells on one side of a point along the group, by the number of mines, for each
+// of the sums the constraints which cross the point have on that side. The log of how much they were scaled down
+// by goes with them.
+struct Layer {
+    ways: HashMap<Vec<i16>, Vec<f64>>,
+    scale: f64,
+}
+
+// the constraints of a group as positions in the order its cells are counted in
+struct Frontier {
+    cells: usize,
+    mines: Vec<i16>,
+    size: Vec<usize>,
+    positions: Vec<Vec<usize>>,
+    first: Vec<usize>,
+    last: Vec<usize>,
+    by_position: Vec<Vec<usize>>,
+    // the constraints with cells on both sides of each point, the point before each cell and the one after the last
+    crossing: Vec<Vec<usize>>,
+}
+
+impl Frontier {
+    fn new(cells: &[u32], constraints: &[&Constraint]) -> Self {
+        let position: BTreeMap<u32, usize> = cells
+            .iter()
+            .enumerate()
+            .map(|(position, cell)| (*cell, position))
+            .collect();
+        let mut frontier = Frontier {
+            cells: cells.len(),
+            mines: Vec::new(),
+            size: Vec::new(),
+            positions: Vec::new(),
+            first: Vec::new(),
+            last: Vec::new(),
+            by_position: vec![Vec::new(); cells.len()],
+            crossing: Vec::new(),
+        };
+        for (index, constraint) in constraints.iter().enumerate() {
+            let mut positions: Vec<usize> =
+                constraint.cells.iter().map(|cell| position[cell]).collect();
+            positions.sort();
+            for position in positions.iter() {
+                frontier.by_position[*position].push(index);
+            }
+            frontier.mines.push(constraint.mines);
+            frontier.size.push(positions.len());
+            frontier.first.push(positions[0]);
+            frontier.last.push(positions[positions.len() - 1]);
+            frontier.positions.push(positions);
+        }
+        frontier.crossing = (0..=cells.len())
+            .map(|point| {
+                (0..constraints.len())
+                    .filter(|index| {
+                        frontier.first[*index] < point && point <= frontier.last[*index]
+                    })
+                    .collect()
+            })
+            .collect();
+        frontier
+    }
+
+    // the layer after the cell at the position, from the one before it, with the cell as each of the choices
+    fn forward(&self, layer: &Layer, position: usize, choices: &[bool]) -> Layer {
+        let (before, after) = (&self.crossing[position], &self.crossing[position + 1]);
+        let mut ways = HashMap::new();
+        for (state, counts) in layer.ways.iter() {
+            for mine in choices.iter() {
+                let mut sums: BTreeMap<usize, i16> =
+                    before.iter().cloned().zip(state.iter().cloned()).collect();
+                for index in self.by_position[position].iter() {
+                    *sums.entry(*index).or_insert(0) += *mine as i16;
+                }
+                // a constraint has to be met by the time its last cell is counted
+                let possible = self.by_position[position].iter().all(|index| {
+                    let sum = sums[index];
+                    let seen = self.seen_by(*index, position + 1);
+                    sum <= self.mines[*index]
+                        && sum + (self.size[*index] - seen) as i16 >= self.mines[*index]
+                });
+                if possible {
+                    let key = after.iter().map(|index| sums[index]).collect();
+                    add_shifted(&mut ways, key, counts, *mine as usize);
+                }
+            }
+        }
+        normalized(ways, layer.scale)
+    }
+
+    // the layer before the cell at the position, from the one after it
+    fn backward(&self, layer: &Layer, position: usize) -> Layer {
+        let (before, after) = (&self.crossing[position], &self.crossing[position + 1]);
+        let mut ways = HashMap::new();
+        for (state, counts) in layer.ways.iter() {
+            for mine in [false, true].iter() {
+                let mut sums: BTreeMap<usize, i16> =
+                    after.iter().cloned().zip(state.iter().cloned()).collect();
+                for index in self.by_position[position].iter() {
+                    *sums.entry(*index).or_insert(0) += *mine as i16;
+                }
+                let possible = self.by_position[position].iter().all(|index| {
+                    let sum = sums[index];
+                    let unseen = self.seen_by(*index, position);
+                    sum <= self.mines[*index] && sum + unseen as i16 >= self.mines[*index]
+                });
+                if possible {
+                    let key = before.iter().map(|index| sums[index]).collect();
+                    add_shifted(&mut ways, key, counts, *mine as usize);
+                }
+            }
+        }
+        normalized(ways, layer.scale)
+    }
+
+    // how many of the constraint's cells come before the point
+    fn seen_by(&self, index: usize, point: usize) -> usize {
+        self.positions[index]
+            .iter()
+            .take_while(|position| **position < point)
+            .count()
+    }
+
+    // the ways for the whole group, from the layers either side of a point, by the number of mines
+    fn join(&self, before: &Layer, after: &Layer, point: usize) -> (Vec<f64>, f64) {
+        let mut ways = vec![0.0; self.cells + 1];
+        for (state, counts) in before.ways.iter() {
+            let rest: Vec<i16> = self.crossing[point]
+                .iter()
+                .zip(state.iter())
+                .map(|(index, sum)| self.mines[*index] - sum)
+                .collect();
+            if let Some(others) = after.ways.get(&rest) {
+                for (total, ways_to) in convolve(counts, others).into_iter().enumerate() {
+                    ways[total] += ways_to;
+                }
+            }
+        }
+        (ways, before.scale + after.scale)
+    }
+}
+
+// add the counts to the state's, with each total moved up by the mines
+fn add_shifted(
+    ways: &mut HashMap<Vec<i16>, Vec<f64>>,
+    key: Vec<i16>,
+    counts: &[f64],
+    mines: usize,
+) {
+    let entry = ways
+        .entry(key)
+        .or_insert_with(|| vec![0.0; counts.len() + 1]);
+    for (total, count) in counts.iter().enumerate() {
+        entry[total + mines] += count;
+    }
+}
+
+// the layer scaled so that its biggest count is 1
+fn normalized(mut ways: HashMap<Vec<i16>, Vec<f64>>, scale: f64) -> Layer {
+    let largest = ways
+        .values()
+        .flat_map(|counts| counts.iter())
+        .fold(0.0, |largest: f64, count| largest.max(*count));
+    if largest > 0.0 {
+        for counts in ways.values_mut() {
+            for count in counts.iter_mut() {
+                *count /= largest;
+            }
+        }
+    }
+    Layer {
+        ways: ways,
+        scale: scale + largest.max(f64::MIN_POSITIVE).ln(),
+    }
+}
+
+// the cells in an order which keeps the constraints part way through few: a breadth first search along the
+// cells which share constraints, from a cell at one end of the group
+fn frontier_order(cells: &[u32], constraints: &[&Constraint]) -> Vec<u32> {
+    let mut neighbors: BTreeMap<u32, BTreeSet<u32>> = BTreeMap::new();
+    for constraint in constraints.iter() {
+        for cell in constraint.cells.iter() {
+            let others = constraint.cells.iter().filter(|other| *other != cell);
+            neighbors.entry(*cell).or_default().extend(others);
+        }
+    }
+    let search = |start: u32| {
+        let mut seen: BTreeSet<u32> = vec![start].into_iter().collect();
+        let mut pending: VecDeque<u32> = vec![start].into_iter().collect();
+        let mut order = Vec::new();
+        while let Some(cell) = pending.pop_front() {
+            order.push(cell);
+            for neighbor in neighbors[&cell].iter() {
+                if seen.insert(*neighbor) {
+                    pending.push_back(*neighbor);
+                }
+            }
+        }
+        order
+    };
+    // the furthest cell from any other is at an end
+    let end = *search(cells[0]).last().unwrap();
+    search(end)
+}
+
+impl Counts {
+    fn new(cells: Vec<u32>, constraints: &[&Constraint]) -> Self {
+        let cells = frontier_order(&cells, constraints);
+        let frontier = Frontier::new(&cells, constraints);
+        let start = || Layer {
+            ways: vec![(Vec::new(), vec![1.0])].into_iter().collect(),
+            scale: 0.0,
+        };
+
+        // the ways up to each point, and from each point to the end
+        let mut forward = vec![start()];
+        for position in 0..cells.len() {
+            let layer = frontier.forward(&forward[position], position, &[false, true]);
+            forward.push(layer);
+        }
+        let mut backward = vec![start()];
+        for position in (0..cells.len()).rev() {
+            let layer = frontier.backward(&backward[0], position);
+            backward.insert(0, layer);
+        }
+
+        // everything is kept in proportion to the ways for the whole group
+        let (ways, scale) = frontier.join(&forward[0], &backward[0], 0);
+        let mut mines = vec![vec![0.0; cells.len()]; cells.len() + 1];
+        for position in 0..cells.len() {
+            let mine = frontier.forward(&forward[position], position, &[true]);
+            let (with_mine, mine_scale) =
+                frontier.join(&mine, &backward[position + 1], position + 1);
+            let proportion = (mine_scale - scale).exp();
+            for (total, ways) in with_mine.into_iter().enumerate() {
+                mines[total][position] = ways * proportion;
+            }
+        }
+        Counts {
+            cells: cells,
+            ways: ways,
+            mines: mines,
+        }
+    }
+}
+
+// the number of ways of placing mines among some groups, by the total number of mines
+fn convolve(first: &[f64], second: &[f64]) -> Vec<f64> {
+    let mut ways = vec![0.0; first.len() + second.len() - 1];
+    for (i, a) in first.iter().enumerate() {
+        for (j, b) in second.iter().enumerate() {
+            ways[i + j] += a * b;
+        }
+    }
+    ways
+}
+
+// the log of n choose k for each k, worked out as logs as the numbers are far too big for a float on big boards
+fn log_choose(n: usize) -> Vec<f64> {
+    let mut logs = vec![0.0; n + 1];
+    for k in 1..=n {
+        logs[k] = logs[k - 1] + ((n - k + 1) as f64).ln() - (k as f64).ln();
+    }
+    logs
+}
+
+// whether each revealed number can still have its mines, with what is known about the cells around it
+fn agrees(options: Options, cells: &[CellView], known: &[Option<bool>]) -> bool {
+    cells.iter().enumerate().all(|(index, cell)| match cell {
+        CellView::Revealed(count) => {
+            let (mut mines, mut unknown) = (0, 0);
+            options.for_each_neighbor(index as u32, |row, column| {
+                match known[options.index(row, column) as usize] {
+                    Some(true) => mines += 1,
+                    None => unknown += 1,
+                    Some(false) => (),
+                }
+            });
+            mines <= *count && *count <= mines + unknown
+        }
+        _ => true,
+    })
+}
+
+// the probability of each cell, or None if the cells can't agree with the numbers and the number of mines
+fn count(options: Options, views: &[CellView]) -> Option<Vec<Option<f64>>> {
+    // the cells that follow from the rules don't need counting
+    let solver = Solver::new(options, views.to_vec());
+    let known = solver.deduce();
+    let constraints = solver.constraints(&known);
+    let groups: Vec<Counts> = components(&constraints)
+        .into_iter()
+        .map(|component| Counts::new(cells(&component), &component))
+        .collect();
+
+    // the cells which aren't in a group can have any of the mines left over
+    let grouped: BTreeSet<u32> = groups
+        .iter()
+        .flat_map(|group| group.cells.clone())
+        .collect();
+    let free = (0..views.len() as u32)
+        .filter(|index| known[*index as usize].is_none() && !grouped.contains(index))
+        .count();
+    let left =
+        options.mines() as i64 - known.iter().filter(|cell| **cell == Some(true)).count() as i64;
+    if left < 0 || !agrees(options, views, &known) {
+        return None;
+    }
+    // the weight of the groups having total mines between them is the ways to place the rest in the free cells
+    let choose = log_choose(free);
+    let weight = |total: usize| -> Option<f64> {
+        let rest = left - total as i64;
+        if rest < 0 || rest > free as i64 {
+            None
+        } else {
+            Some(choose[rest as usize])
+        }
+    };
+
+    // the ways for every group, and for every group but each one
+    let all = groups.iter().fold(vec![1.0], |ways: Vec<f64>, group| {
+        convolve(&ways, &group.ways)
+    });
+    let largest = (0..all.len())
+        .filter(|total| all[*total] > 0.0)
+        .filter_map(|total| weight(total))
+        .fold(f64::NEG_INFINITY, f64::max);
+    if largest == f64::NEG_INFINITY {
+        return None;
+    }
+    let scale = |total: usize| weight(total).map_or(0.0, |weight| (weight - largest).exp());
+    let sum: f64 = (0..all.len()).map(|total| all[total] * scale(total)).sum();
+
+    let mut probabilities: Vec<Option<f64>> = known
+        .iter()
+        .map(|cell| cell.map(|mine| mine as u8 as f64))
+        .collect();
+    for (index, group) in groups.iter().enumerate() {
+        let others = groups
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index)
+            .fold(vec![1.0], |ways: Vec<f64>, (_, group)| {
+                convolve(&ways, &group.ways)
+            });
+        for (position, cell) in group.cells.iter().enumerate() {
+            let mut mines = 0.0;
+            for (own, counts) in group.mines.iter().enumerate() {
+                for (rest, ways) in others.iter().enumerate() {
+                    mines += counts[position] * ways * scale(own + rest);
+                }
+            }
+            probabilities[*cell as usize] = Some(mines / sum);
+        }
+    }
+    if free > 0 {
+        let mines: f64 = (0..all.len())
+            .map(|total| all[total] * scale(total) * (left - total as i64) as f64)
+            .sum();
+        let probability = mines / sum / free as f64;
+        for (index, cell) in known.iter().enumerate() {
+            if cell.is_none() && !grouped.contains(&(index as u32)) {
+                probabilities[index] = Some(probability);
+            }
+        }
+    }
+    Some(probabilities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Probabilities;
+    use crate::config::Options;
+    use crate::model::{Board, CellView};
+
+    fn probabilities_of(text: &str) -> Vec<Option<f64>> {
+        let board: Board = text.parse().unwrap();
+        let probabilities = Probabilities::from_board(&board);
+        let tiles = board.options().tiles() as u32;
+        (0..tiles).map(|index| probabilities.get(index)).collect()
+    }
+
+    fn assert_near(probability: Option<f64>, expected: f64) {
+        let probability = probability.unwrap();
+        assert!(
+            (probability - expected).abs() < 1e-9,
+            "{} isn't {}",
+            probability,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_certain() {
+        let probabilities = probabilities_of("*1\n11\n..\n");
+        assert_near(probabilities[0], 1.0);
+        assert_eq!(probabilities[1], None);
+        assert_near(probabilities[4], 0.0);
+        assert_near(probabilities[5], 0.0);
+    }
+
+    #[test]
+    fn test_mine_count() {
+        // the 1 is a coin toss between two cells, and the other mine is in one of the six cells left
+        let probabilities = probabilities_of(
+            "\
+            1*...\n\
+            1.*..\n",
+        );
+        assert_near(probabilities[1], 0.5);
+        assert_near(probabilities[6], 0.5);
+        for index in [2, 3, 4, 7, 8, 9].iter() {
+            assert_near(probabilities[*index], 1.0 / 6.0);
+        }
+
+        // the 1s have a mine between them or one on each side, and with one mine there are three ways to place
+        // the other in the cells left over, but none with two
+        let options = Options::custom(1, 8, 2).unwrap();
+        let (hidden, one) = (CellView::Hidden, CellView::Revealed(1));
+        let cells = [hidden, one, hidden, one, hidden, hidden, hidden, hidden];
+        let probabilities = Probabilities::new(options, &cells);
+        assert_near(probabilities.get(0), 0.25);
+        assert_near(probabilities.get(2), 0.75);
+        assert_near(probabilities.get(4), 0.25);
+        assert_near(probabilities.get(6), 0.25);
+    }
+
+    #[test]
+    fn test_flags() {
+        // a right flag is a mine, and leaves fewer to place
+        let probabilities = probabilities_of("1F.\n1..\n");
+        assert_near(probabilities[1], 1.0);
+        assert_near(probabilities[2], 0.0);
+        assert_near(probabilities[4], 0.0);
+
+        // a flag which can't be right is left out
+        let probabilities = probabilities_of("0f*\n0..\n");
+        assert_near(probabilities[1], 0.0);
+        assert_near(probabilities[2], 0.5);
+        assert_near(probabilities[5], 0.5);
+    }
+
+    #[test]
+    fn test_sums() {
+        // the probabilities over the hidden cells add up to the number of mines left
+        let board: Board = "\
+            .*...*\n\
+            *..*..\n\
+            12*...\n\
+            01...*\n"
+            .parse()
+            .unwrap();
+        let probabilities = Probabilities::from_board(&board);
+        let sum: f64 = (0..24).filter_map(|index| probabilities.get(index)).sum();
+        assert!((sum - 6.0).abs() < 1e-9);
+        assert_near(probabilities.get(8), 0.0);
+    }
+
+    #[test]
+    fn test_long_frontier() {
+        // the 1s along the bottom leave the mines either under every cell that's 1 along from a multiple of 3, or
+        // under every multiple of 3, 17 mines either way, which leaves 5 for the top row. Every number counts,
+        // however long the edge of the revealed area is.
+        let columns = 50;
+        let top: String = (0..columns)
+            .map(|column| if column % 10 == 0 { '*' } else { '.' })
+            .collect();
+        let middle: String = (0..columns)
+            .map(|column| if column % 3 == 1 { '*' } else { '.' })
+            .collect();
+        let bottom: String = (0..columns).map(|_| '1').collect();
+        let probabilities = probabilities_of(&format!("{}\n{}\n{}\n", top, middle, bottom));
+        for column in 0..columns {
+            assert_near(probabilities[column], 0.1);
+            let expected = if column % 3 == 2 { 0.0 } else { 0.5 };
+            assert_near(probabilities[columns + column], expected);
+            assert_eq!(probabilities[2 * columns + column], None);
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::config::Options;
+use crate::model::{Board, CellView};
+use crate::solver::constraint::{cells, components, enumerate, Constraint};
+
+// the most cells a group of constraints can have for the exhaustive search, which doubles in time with each
+const SEARCH_LIMIT: usize = 24;
+
+/// What the solver is certain of, the indices of the hidden cells which are safe and which are mines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Solution {
+    pub safe: BTreeSet<u32>,
+    pub mines: BTreeSet<u32>,
+}
+
+impl Solution {
+    pub fn is_empty(&self) -> bool {
+        self.safe.is_empty() && self.mines.is_empty()
+    }
+}
+
+/**
+ * Solver works out which hidden cells are certainly safe and which are certainly mines, from what the player
+ * can see: the revealed numbers, the hidden cells and the number of mines. Flags are only the player's guesses,
+ * so they're treated as hidden, a flag the solver agrees with shows up in the mines. Question marks are hidden too.
+ *
+ * It applies the rules a player would, cheapest first, and starts again whenever one of them finds something:
+ * a number with as many hidden neighbors as mines, or none left to find; two numbers whose neighbors overlap;
+ * the mines left over the whole board; and, for groups of numbers which share cells, a search of every way
+ * the mines could be placed, with the fewest and most mines the groups can have compared with the mines left.
+ */
+pub struct Solver {
+    options: Options,
+    cells: Vec<CellView>,
+    search_limit: usize,
+}
+
+impl Solver {
+    /// Create a solver for the cells, as the player sees them, of a board with the options.
+    pub fn new(options: Options, cells: Vec<CellView>) -> Self {
+        Self {
+            options: options,
+            cells: cells,
+            search_limit: SEARCH_LIMIT,
+        }
+    }
+
+    /// Create a solver for the board as the player sees it.
+    pub fn from_board(board: &Board) -> Self {
+        let options = *board.options();
+        let cells = (0..options.tiles() as u32)
+            .map(|index| board.cell_at(index))
+            .collect();
+        Solver::new(options, cells)
+    }
+
+    /// Change the most cells the exhaustive search will take on at once, 0 turns it off.
+    pub fn with_search_limit(mut self, search_limit: usize) -> Self {
+        self.search_limit = search_limit;
+        self
+    }
+
+    pub fn solve(&self) -> Solution {
+        let known = self.deduce();
+        let mut solution = Solution::default();
+        for (index, cell) in self.cells.iter().enumerate() {
+            match (cell, known[index]) {
+                (CellView::Revealed(_), _) | (CellView::Mine, _) => (),
+                (_, Some(false)) => {
+                    solution.safe.insert(index as u32);
+                }
+                (_, Some(true)) => {
+                    solution.mines.insert(index as u32);
+                }
+                (_, None) => (),
+            }
+        }
+        solution
+    }
+
+    /// What is certain about each cell, Some(true) for a mine, including the revealed cells.
+    pub fn deduce(&self) -> Vec<Option<bool>> {
+        let mut known: Vec<Option<bool>> = self
+            .cells
+            .iter()
+            .map(|cell| match cell {
+                CellView::Revealed(_) => Some(false),
+                CellView::Mine => Some(true),
+                _ => None,
+            })
+            .collect();
+        loop {
+            let constraints = self.constraints(&known);
+            let found = self
+                .single(&constraints)
+                .or_else(|| self.pairs(&constraints))
+                .or_else(|| self.mine_count(&known))
+                .or_else(|| self.search(&known, &constraints));
+            match found {
+                Some(found) => {
+                    for (index, mine) in found {
+                        known[index as usize] = Some(mine);
+                    }
+                }
+                None => return known,
+            }
+        }
+    }
+
+    /// What each revealed number says about the cells around it which aren't known yet.
+    pub fn constraints(&self, known: &[Option<bool>]) -> Vec<Constraint> {
+        let options = self.options;
+        let mut constraints = Vec::new();
+        for (index, cell) in self.cells.iter().enumerate() {
+            if let CellView::Revealed(count) = cell {
+                let mut constraint = Constraint {
+                    cells: BTreeSet::new(),
+                    mines: *count as i16,
+                };
+                options.for_each_neighbor(index as u32, |row, column| {
+                    let neighbor = options.index(row, column);
+                    match known[neighbor as usize] {
+                        None => {
+                            constraint.cells.insert(neighbor);
+                        }
+                        Some(true) => constraint.mines -= 1,
+                        Some(false) => (),
+                    }
+                });
+                if !constraint.cells.is_empty() {
+                    constraints.push(constraint);
+                }
+            }
+        }
+        constraints.sort();
+        constraints.dedup();
+        constraints
+    }
+
+    // a number whose mines have all been found, or whose hidden neighbors are all mines
+    fn single(&self, constraints: &[Constraint]) -> Option<Vec<(u32, bool)>> {
+        let found: Vec<(u32, bool)> = constraints
+            .iter()
+            .filter(|constraint| constraint.is_safe() || constraint.is_mines())
+            .flat_map(|constraint| {
+                let mine = !constraint.is_safe();
+                constraint.cells.iter().map(move |cell| (*cell, mine))
+            })
+            .collect();
+        some(found)
+    }
+
+    // two numbers which share cells: when the second has as many more mines than the first as it has cells of
+    // its own, those are all mines and the first's own cells are safe
+    fn pairs(&self, constraints: &[Constraint]) -> Option<Vec<(u32, bool)>> {
+        let mut by_cell: BTreeMap<u32, Vec<&Constraint>> = BTreeMap::new();
+        for constraint in constraints.iter() {
+            for cell in constraint.cells.iter() {
+                by_cell.entry(*cell).or_default().push(constraint);
+            }
+        }
+        let mut found = Vec::new();
+        for first in constraints.iter() {
+            let overlapping: BTreeSet<&Constraint> = first
+                .cells
+                .iter()
+                .flat_map(|cell| by_cell[cell].iter().cloned())
+                .collect();
+            for second in overlapping {
+                let own: Vec<&u32> = second.cells.difference(&first.cells).collect();
+                if second != first
+                    && !own.is_empty()
+                    && second.mines - first.mines == own.len() as i16
+                {
+                    found.extend(own.into_iter().map(|cell| (*cell, true)));
+                    found.extend(
+                        first
+                            .cells
+                            .difference(&second.cells)
+                            .map(|cell| (*cell, false)),
+                    );
+                }
+            }
+        }
+        some(found)
+    }
+
+    // the mines left over the whole board, when they're all found or every unknown cell is one
+    fn mine_count(&self, known: &[Option<bool>]) -> Option<Vec<(u32, bool)>> {
+        let mines = known.iter().filter(|cell| **cell == Some(true)).count() as i32;
+        let unknown: Vec<u32> = (0..known.len() as u32)
+            .filter(|index| known[*index as usize].is_none())
+            .collect();
+        let left = self.options.mines() - mines;
+        if !unknown.is_empty() && (left == 0 || left == unknown.len() as i32) {
+            Some(unknown.into_iter().map(|cell| (cell, left > 0)).collect())
+        } else {
+            None
+        }
+    }
+
+    // every way the mines could be placed around each group of numbers, a cell which is the same in all of them
+    // is known. Between them the groups need some of the mines left over the whole board: when they need every
+    // one, the cells outside the groups are safe and each group has as few as it can, and when the cells outside
+    // have to take every mine the groups can't, they're all mines and each group has as many as it can.
+    fn search(
+        &self,
+        known: &[Option<bool>],
+        constraints: &[Constraint],
+    ) -> Option<Vec<(u32, bool)>> {
+        let mut groups = Vec::new();
+        // the mines left are only compared when every group was searched and adds up
+        let mut counted = true;
+        for component in components(constraints) {
+            let cells = cells(&component);
+            if cells.len() > self.search_limit {
+                counted = false;
+                continue;
+            }
+            // the ways with each number of mines, and how many of them have each cell as a mine
+            let mut by_total: BTreeMap<usize, (usize, Vec<usize>)> = BTreeMap::new();
+            enumerate(&cells, &component, &mut |assignment| {
+                let total = assignment.iter().filter(|mine| **mine).count();
+                let (solutions, mines) = by_total
+                    .entry(total)
+                    .or_insert_with(|| (0, vec![0; assignment.len()]));
+                *solutions += 1;
+                for (count, mine) in mines.iter_mut().zip(assignment) {
+                    *count += *mine as usize;
+                }
+            });
+            // a board which doesn't add up has no solutions, and nothing can be said about it
+            if by_total.is_empty() {
+                counted = false;
+                continue;
+            }
+            groups.push((cells, by_total));
+        }
+        let mut found = Vec::new();
+        let grouped: BTreeSet<u32> = constraints
+            .iter()
+            .flat_map(|constraint| constraint.cells.iter().cloned())
+            .collect();
+        let outside: Vec<u32> = (0..known.len() as u32)
+            .filter(|index| known[*index as usize].is_none() && !grouped.contains(index))
+            .collect();
+        let left =
+            self.options.mines() - known.iter().filter(|cell| **cell == Some(true)).count() as i32;
+        let fewest: usize = groups
+            .iter()
+            .map(|(_, by_total)| by_total.keys().next().unwrap())
+            .sum();
+        let most: usize = groups
+            .iter()
+            .map(|(_, by_total)| by_total.keys().next_back().unwrap())
+            .sum();
+        let needs_fewest = counted && fewest as i32 == left;
+        let needs_most = counted && (most + outside.len()) as i32 == left;
+        if needs_fewest || needs_most {
+            found.extend(outside.iter().map(|cell| (*cell, !needs_fewest)));
+        }
+        for (cells, by_total) in groups {
+            let ways: Vec<&(usize, Vec<usize>)> = if needs_fewest {
+                by_total.values().take(1).collect()
+            } else if needs_most {
+                by_total.values().rev().take(1).collect()
+            } else {
+                by_total.values().collect()
+            };
+            let solutions: usize = ways.iter().map(|(solutions, _)| solutions).sum();
+            for (position, cell) in cells.iter().enumerate() {
+                let count: usize = ways.iter().map(|(_, mines)| mines[position]).sum();
+                if count == 0 || count == solutions {
+                    found.push((*cell, count > 0));
+                }
+            }
+        }
+        some(found)
+    }
+}
+
+/// Reveal the cells the solver is sure are safe, over and over until it isn't sure of any more. A board which can
+/// be solved without guessing is won.
+pub fn play_safe_moves(board: &mut Board) {
+    let options = *board.options();
+    loop {
+        let solution = Solver::from_board(board).solve();
+        if solution.safe.is_empty() {
+            return;
+        }
+        for cell in solution.safe {
+            let (row, column) = options.row_column(cell);
+            board.reveal(row, column);
+        }
+    }
+}
+
+fn some(found: Vec<(u32, bool)>) -> Option<Vec<(u32, bool)>> {
+    if found.is_empty() {
+        None
+    } else {
+        Some(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{play_safe_moves, Solver};
+    use crate::config::{FirstClick, INTERMEDIATE_OPTIONS};
+    use crate::model::{Board, GameState};
+
+    fn solve(text: &str) -> (Vec<u32>, Vec<u32>) {
+        let board: Board = text.parse().unwrap();
+        let solution = Solver::from_board(&board).solve();
+        let mines = board.mines().unwrap();
+        // whatever it finds has to be right
+        assert!(solution.safe.iter().all(|cell| !mines.contains(cell)));
+        assert!(solution.mines.iter().all(|cell| mines.contains(cell)));
+        (
+            solution.safe.into_iter().collect(),
+            solution.mines.into_iter().collect(),
+        )
+    }
+
+    #[test]
+    fn test_single() {
+        // the top 1 only has the corner next to it, which is all the mines the 1 under it has
+        let (safe, mines) = solve(
+            "\
+            *1\n\
+            11\n\
+            ..\n",
+        );
+        assert_eq!((safe, mines), (vec![4, 5], vec![0]));
+    }
+
+    #[test]
+    fn test_pairs() {
+        // a 1-2-1 against the wall, the 2 has one cell more than each 1 so it's a mine
+        let (safe, mines) = solve(
+            "\
+            *.*\n\
+            121\n\
+            000\n",
+        );
+        assert_eq!((safe, mines), (vec![1], vec![0, 2]));
+    }
+
+    #[test]
+    fn test_mine_count() {
+        // once the corner is found there are no mines left, even away from the numbers
+        let (safe, mines) = solve(
+            "\
+            *1..\n\
+            11..\n\
+            ....\n",
+        );
+        assert_eq!((safe, mines), (vec![2, 3, 6, 7, 8, 9, 10, 11], vec![0]));
+    }
+
+    #[test]
+    fn test_search() {
+        // the two 1s are one mine each, all of the mines under the 2 between them, which takes all three
+        let board: Board = "\
+            .*...*\n\
+            *..*..\n\
+            12*...\n\
+            01...*\n"
+            .parse()
+            .unwrap();
+        let solution = Solver::from_board(&board).with_search_limit(0).solve();
+        assert!(solution.is_empty());
+        let solution = Solver::from_board(&board).solve();
+        assert_eq!(solution.safe.into_iter().collect::<Vec<u32>>(), vec![8]);
+        assert!(solution.mines.is_empty());
+    }
+
+    #[test]
+    fn test_search_mine_count() {
+        // the 2 needs two of the three mines and the 1 the last, so none are left for the cells between them
+        let (safe, mines) = solve(
+            "\
+            .2...\n\
+            **...\n\
+            .....\n\
+            ..*..\n\
+            ...1.\n",
+        );
+        assert_eq!(safe, vec![3, 4, 8, 9, 10, 11, 12, 13, 14, 15, 16, 20, 21]);
+        assert!(mines.is_empty());
+    }
+
+    #[test]
+    fn test_play() {
+        // playing only the moves the solver is sure of never hits a mine
+        let options = INTERMEDIATE_OPTIONS.with_first_click(FirstClick::Opening);
+        let mut won = 0;
+        for seed in 0..20 {
+            let mut board = Board::new(options.with_seed(seed));
+            board.reveal(8, 8);
+            play_safe_moves(&mut board);
+            assert_ne!(board.state(), GameState::Lose);
+            let solution = Solver::from_board(&board).solve();
+            let mines = board.mines().unwrap();
+            assert!(solution.mines.iter().all(|cell| mines.contains(cell)));
+            if board.state() == GameState::Win {
+                won += 1;
+            }
+        }
+        assert!(won > 0);
+    }
+}
@@ -1,16 +1,28 @@
 pub mod sprites;
 pub use sprites::render_digit;
-pub use sprites::StartTimeInvalid;
+pub use sprites::{BoardTooBig, EmptyBoard, InvalidArgument, MineCount, UnknownFlag, UnknownLevel};
+pub use sprites::{InvalidBoard, InvalidReplay, InvalidSave, InvalidWiring};
 
 pub use sprites::Error;
 
 pub mod channel_wiring;
 
+pub mod queue;
+pub use queue::{Schedule, Sender};
+
+mod trace;
+pub use trace::{Trace, TraceEvent, TraceRecord};
+
 pub mod message_exchange;
 pub use message_exchange::{ChannelMessage, ChannelWiring, Exchange, MessageExchange};
 
 pub use sprites::GameState;
-pub use sprites::{MouseButton, MouseEventData, Renderer, RendererContext, Sprite};
+pub use sprites::{Color, MouseAction, MouseButton, MouseButtons, MouseEventData};
+pub use sprites::{Renderer, RendererContext, Sprite};
+
+mod damage;
+pub use damage::Damage;
+pub use sprites::ResumeData;
 
 pub mod background;
 pub use background::Background;
@@ -30,14 +42,8 @@ pub use timecounter::TimeCounter;
 pub mod tile;
 pub use tile::Tile;
 
-#[cfg(feature = "media_layer_wasm")]
 mod util;
+pub use util::{Point, Rect};
 
-#[cfg(feature = "media_layer_wasm")]
-pub use util::{Point, Rect, SystemTime};
-
-#[cfg(feature = "media_layer_sdl2")]
-pub use sdl2::rect::{Point, Rect};
-
-#[cfg(feature = "media_layer_sdl2")]
-pub type SystemTime = std::time::SystemTime;
+pub mod clock;
+pub use clock::{Clock, InstantClock};
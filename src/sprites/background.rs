@@ -1,46 +1,70 @@
+use crate::config::Layout;
 use crate::sprites::Rect;
 use crate::sprites::{ChannelMessage, ChannelWiring, Exchange, MessageExchange};
-use crate::sprites::{Error, Renderer, RendererContext, Sprite};
+use crate::sprites::{Color, Error, Renderer, RendererContext, Sprite};
 
-// Background sprite is pretty simple
+const FACE: Color = Color::rgb(189, 189, 189);
+const HIGHLIGHT: Color = Color::rgb(255, 255, 255);
+const SHADOW: Color = Color::rgb(123, 123, 123);
+
+// Background sprite is pretty simple, it never changes so it is only drawn once
 pub struct Background {
     exchange: Exchange,
+    drawn: bool,
 }
 
 impl Background {
     pub fn new(wiring: &mut ChannelWiring) -> Self {
         Self {
             exchange: Exchange::new_from_wiring::<Background>(wiring),
+            drawn: false,
         }
     }
+
+    // The frame is drawn to fit the layout, so any size of board gets one:
+    // a raised window holding a sunken header and a sunken grid.
+    fn render_frame(context: &dyn RendererContext) -> Result<(), String> {
+        let layout = context.layout();
+        let window = Rect::new(0, 0, layout.width(), layout.height());
+        context.fill_rect(window, FACE)?;
+        Background::render_bevel(context, window, HIGHLIGHT, SHADOW)?;
+        Background::render_bevel(context, layout.header(), SHADOW, HIGHLIGHT)?;
+        Background::render_bevel(context, layout.grid_frame(), SHADOW, HIGHLIGHT)
+    }
+
+    fn render_bevel(
+        context: &dyn RendererContext,
+        rect: Rect,
+        top_left: Color,
+        bottom_right: Color,
+    ) -> Result<(), String> {
+        let (width, height) = (rect.width(), rect.height());
+        let bevel = Layout::bevel();
+        // bottom and right first, so that the top and left win in the corners
+        let bottom = Rect::new(rect.left(), rect.bottom() - bevel as i32, width, bevel);
+        context.fill_rect(bottom, bottom_right)?;
+        let right = Rect::new(rect.right() - bevel as i32, rect.top(), bevel, height);
+        context.fill_rect(right, bottom_right)?;
+        context.fill_rect(Rect::new(rect.left(), rect.top(), width, bevel), top_left)?;
+        context.fill_rect(Rect::new(rect.left(), rect.top(), bevel, height), top_left)
+    }
 }
 
 impl Renderer for Background {
     fn render(&self, context: &dyn RendererContext) -> Result<(), Error> {
-        let base = context.layout().options.level();
-        let name = format!("bg_{}", base);
-        let image = context.load(&name)?;
-        let w = context.layout().width();
-        let h = context.layout().height();
-        let rect = Rect::new(0, 0, w, h);
-        context.render_image(&image, None, rect)?;
+        Background::render_frame(context)?;
         Ok(())
     }
 }
 
 impl MessageExchange for Background {
     fn pull(&mut self) -> u32 {
-        let mut count = self.exchange.pull();
+        let count = self.exchange.pull();
         for message in self.exchange.get_messages().iter() {
             match message {
-                ChannelMessage::Render(context) => {
-                    let base = context.layout().options.level();
-                    let name = format!("bg_{}", base);
-                    let image = context.load(&name).unwrap();
-                    let w = context.layout().width();
-                    let h = context.layout().height();
-                    let rect = Rect::new(0, 0, w, h);
-                    context.render_image(&image, None, rect).unwrap();
+                ChannelMessage::Render(context) if !self.drawn => {
+                    self.render(context.as_ref().as_ref()).unwrap();
+                    self.drawn = true;
                 }
                 _ => (),
             }
@@ -1,15 +1,21 @@
 use crate::config::Layout;
 use crate::sprites::GameState;
 use crate::sprites::{Error, Rect};
-use crate::sprites::{MouseButton, MouseEventData, Renderer, RendererContext, Sprite};
+use crate::sprites::{MouseAction, MouseButton, MouseEventData, RendererContext, Sprite};
 
 use crate::sprites::{ChannelMessage, ChannelWiring, Exchange, MessageExchange};
 
+/**
+ * Button is the face above the board, which starts a new game when it's clicked, or pressed and let go of over
+ * it. It shows how the game is going, and looks surprised while a tile is held down.
+ */
 pub struct Button {
     game_state: GameState,
-    revealed: i16,
-    blanks: i16,
     bounding_box: Rect,
+    grid: Rect,
+    pressed: bool,
+    face_pressed: bool,
+    shown: Option<&'static str>,
     exchange: Exchange,
 }
 
@@ -17,9 +23,11 @@ impl Button {
     pub fn new(layout: Layout, wiring: &mut ChannelWiring) -> Self {
         Self {
             game_state: GameState::Init,
-            revealed: 0,
-            blanks: layout.options.blanks(),
             bounding_box: layout.face(),
+            grid: layout.grid(),
+            pressed: false,
+            face_pressed: false,
+            shown: None,
             exchange: Exchange::new_from_wiring::<Button>(wiring),
         }
     }
@@ -31,17 +39,49 @@ impl Button {
         self.exchange.push_message(message);
     }
 
-    fn render(&self, context: &Box<dyn RendererContext>) -> Result<(), Error> {
+    // the face is only drawn again when it has changed
+    fn render(&mut self, context: &Box<dyn RendererContext>) -> Result<(), Error> {
         let name = match self.game_state {
+            GameState::Init | GameState::Playing if self.pressed => "face_surprised",
             GameState::Init => "face_playing",
             GameState::Playing => "face_playing",
             GameState::Win => "face_win",
             GameState::Lose => "face_lose",
         };
+        if self.shown == Some(name) {
+            return Ok(());
+        }
+        self.shown = Some(name);
         let image = context.load(name)?;
         context.render_image(&image, None, self.bounding_box)?;
         Ok(())
     }
+
+    fn handle_mouse_event(&mut self, event: &MouseEventData) {
+        let on_face = self.bounding_box.contains_point((event.x, event.y));
+        let left = event.mouse_btn == MouseButton::Left;
+        match event.action {
+            // only the left and middle buttons hold a tile down, the right one flags it straight away
+            MouseAction::Press => {
+                self.pressed = event.mouse_btn != MouseButton::Right
+                    && self.grid.contains_point((event.x, event.y));
+                self.face_pressed = left && on_face;
+            }
+            MouseAction::Release => {
+                if self.face_pressed && on_face && left {
+                    self.update_game_state(GameState::Init);
+                }
+                self.pressed = false;
+                self.face_pressed = false;
+            }
+            MouseAction::Click => {
+                if on_face && left {
+                    self.update_game_state(GameState::Init);
+                }
+            }
+            MouseAction::Move => (),
+        }
+    }
 }
 
 impl MessageExchange for Button {
@@ -49,25 +89,11 @@ impl MessageExchange for Button {
         let count = self.exchange.pull();
         for message in self.exchange.get_messages().iter() {
             match message {
-                ChannelMessage::Revealed(true, _) => self.update_game_state(GameState::Lose),
-                ChannelMessage::Revealed(false, _) => {
-                    if self.game_state == GameState::Init {
-                        self.update_game_state(GameState::Playing);
-                    }
-                    self.revealed += 1;
-                    if self.revealed == self.blanks {
-                        self.update_game_state(GameState::Win);
-                    }
-                }
+                ChannelMessage::GameStateChanged(state) => self.update_game_state(*state),
+                // the grid already has the restored board, so there's nobody to tell
+                ChannelMessage::Resume(data) => self.game_state = data.state,
                 ChannelMessage::Render(context) => self.render(&context).unwrap(),
-                ChannelMessage::MouseEvent(event) => {
-                    if self.bounding_box.contains_point((event.x, event.y)) {
-                        if event.mouse_btn == MouseButton::Left {
-                            self.revealed = 0;
-                            self.update_game_state(GameState::Init);
-                        }
-                    }
-                }
+                ChannelMessage::MouseEvent(event) => self.handle_mouse_event(event),
                 _ => (),
             }
         }
@@ -1,15 +1,39 @@
-use std::any::TypeId;
+use std::any::{type_name, TypeId};
 use std::collections::HashMap;
-use std::sync::mpsc::{channel, Receiver, Sender};
-
+use std::path::Path;
+
+use crate::sprites::queue::{Receiver, Schedule, Sender};
+use crate::sprites::{Error, InvalidWiring, Trace};
+
+/**
+ * TheChannelWiring connects types by their TypeId, each type it wires to is given a queue, and the types which
+ * send to it a sender for it. Each type then takes its senders and receiver when it's created.
+ *
+ * Nothing checks that the types agree until messages go missing, so once everything has been created validate
+ * reports whatever was left over: senders or receivers which were never taken, types which were expected to
+ * receive messages but have nothing wired to them, and types which took their channels but have none. The wiring
+ * can be drawn with Graphviz from to_dot.
+ */
 #[derive(Debug, Default)]
 pub struct TheChannelWiring<M> {
-    /// The initial sender, created by channel()
+    /// The initial sender, created by queue()
     channel_senders: HashMap<TypeId, Sender<M>>,
-    /// The initial receiver, created by channel()
+    /// The initial receiver, created by queue()
     channel_receivers: HashMap<TypeId, Receiver<M>>,
     /// The senders that a type will be wired to
     senders: HashMap<TypeId, Vec<Sender<M>>>,
+    /// The order messages are sent in through all of the channels
+    schedule: Schedule,
+    /// Each sender and receiver wired together, in the order they were wired
+    edges: Vec<(TypeId, TypeId)>,
+    /// The readable names of the types, in the order they were first seen
+    names: Vec<(TypeId, String)>,
+    /// The types which have taken their channels
+    taken: Vec<TypeId>,
+    /// The types which have to be sent messages
+    receiving: Vec<TypeId>,
+    /// The trace of the messages sent through the channels, which knows the names of the types
+    trace: Trace,
 }
 
 impl<M> TheChannelWiring<M> {
@@ -21,8 +45,11 @@ impl<M> TheChannelWiring<M> {
     {
         // get the sender for type R, this can create a (tx,rx) pair
         // add it to the senders for type S
-        let tx = self.get_or_create_sender::<R>();
+        let tx = self.get_or_create_sender::<R>().sent_by(TypeId::of::<S>());
         Self::wire_channel::<S, _>(tx, &mut self.senders);
+        self.name::<S>();
+        self.name::<R>();
+        self.edges.push((TypeId::of::<S>(), TypeId::of::<R>()));
     }
 
     /// Get the `Sender`s and `Receiver` for a type
@@ -30,9 +57,140 @@ impl<M> TheChannelWiring<M> {
     where
         T: 'static,
     {
+        self.name::<T>();
+        self.taken.push(TypeId::of::<T>());
         (self.extract_senders::<T>(), self.extract_receiver::<T>())
     }
 
+    /// Note that a type has to be sent messages, so that validate reports it if nothing is wired to it.
+    pub fn expect_receiver<T>(&mut self)
+    where
+        T: 'static,
+    {
+        self.name::<T>();
+        self.receiving.push(TypeId::of::<T>());
+    }
+
+    /// Check that every type has taken its channels, and that the ones which should receive messages are sent
+    /// them. All of the problems are reported together.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut problems = Vec::new();
+        for (id, name) in self.names.iter() {
+            let sends_to =
+                self.wired(|(sender, receiver)| Some(*receiver).filter(|_| sender == id));
+            let sent_by = self.wired(|(sender, receiver)| Some(*sender).filter(|_| receiver == id));
+            if self.senders.contains_key(id) {
+                problems.push(format!(
+                    "{} sends to {} but never took its senders",
+                    name, sends_to
+                ));
+            }
+            if self.channel_receivers.contains_key(id) {
+                problems.push(format!(
+                    "{} is sent messages by {} but never took its receiver",
+                    name, sent_by
+                ));
+            }
+            if self.receiving.contains(id) && sent_by.is_empty() {
+                problems.push(format!(
+                    "{} has no receiver, nothing is wired to send to it",
+                    name
+                ));
+            }
+            if self.taken.contains(id) && sends_to.is_empty() && sent_by.is_empty() {
+                problems.push(format!(
+                    "{} took its channels but isn't wired to anything",
+                    name
+                ));
+            }
+        }
+        snafu::ensure!(
+            problems.is_empty(),
+            InvalidWiring {
+                problems: problems.join("; ")
+            }
+        );
+        Ok(())
+    }
+
+    /// The wiring as a Graphviz DOT graph, with an edge from each type to each type it sends messages to.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph wiring {\n");
+        for (id, name) in self.names.iter() {
+            if !self
+                .edges
+                .iter()
+                .any(|(sender, receiver)| sender == id || receiver == id)
+            {
+                dot.push_str(&format!("    {};\n", dot_id(name)));
+            }
+        }
+        for (sender, receiver) in self.edges.iter() {
+            dot.push_str(&format!(
+                "    {} -> {};\n",
+                dot_id(self.name_of(*sender)),
+                dot_id(self.name_of(*receiver))
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Write the wiring as a Graphviz DOT file.
+    pub fn save_dot(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, self.to_dot())
+            .map_err(|e| Error::from(format!("{}: {}", path.display(), e)))
+    }
+
+    /// The schedule of the messages sent through the channels, which are handled in the order they were sent
+    /// by taking each receiver from it in turn.
+    pub fn schedule(&self) -> Schedule {
+        self.schedule.clone()
+    }
+
+    /// The trace of the messages sent through the channels, it's off until it's turned on.
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    /// Remember the readable name of a type.
+    fn name<T>(&mut self)
+    where
+        T: 'static,
+    {
+        let id = TypeId::of::<T>();
+        if !self.names.iter().any(|(named, _)| *named == id) {
+            let name = readable_name(type_name::<T>());
+            self.trace.name(id, &name);
+            self.names.push((id, name));
+        }
+    }
+
+    fn name_of(&self, id: TypeId) -> &str {
+        self.names
+            .iter()
+            .find(|(named, _)| *named == id)
+            .map_or("?", |(_, name)| name.as_str())
+    }
+
+    /// The names of the other ends of the edges picked out, e.g. "Button and FlagCounter".
+    fn wired<F>(&self, other_end: F) -> String
+    where
+        F: Fn(&(TypeId, TypeId)) -> Option<TypeId>,
+    {
+        let names: Vec<&str> = self
+            .edges
+            .iter()
+            .filter_map(other_end)
+            .map(|id| self.name_of(id))
+            .collect();
+        match names.split_last() {
+            Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+            Some((last, _)) => last.to_string(),
+            None => String::new(),
+        }
+    }
+
     /// Internal function for wiring a
     fn wire_channel<S, T>(channel: T, map: &mut HashMap<TypeId, Vec<T>>)
     where
@@ -57,12 +215,12 @@ impl<M> TheChannelWiring<M> {
         }
     }
 
-    /// Create a channel and return a clone of the sender
+    /// Create a queue and return a clone of the sender
     fn create_channel<R>(&mut self) -> Sender<M>
     where
         R: 'static,
     {
-        let (tx, rx) = channel();
+        let (tx, rx) = self.schedule.queue(TypeId::of::<R>());
         self.channel_senders.insert(TypeId::of::<R>(), tx.clone());
         self.channel_receivers.insert(TypeId::of::<R>(), rx);
         tx
@@ -90,6 +248,34 @@ impl<M> TheChannelWiring<M> {
     }
 }
 
+/// The name of a type without the paths of the modules, e.g. Vec<Grid> for alloc::vec::Vec<crate::sprites::Grid>.
+fn readable_name(name: &str) -> String {
+    let mut readable = String::new();
+    let mut path_start = 0;
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            readable.truncate(path_start);
+        } else {
+            readable.push(c);
+            if !(c.is_alphanumeric() || c == '_') {
+                path_start = readable.len();
+            }
+        }
+    }
+    readable
+}
+
+/// A name as a DOT id, quoted unless it's a plain identifier.
+fn dot_id(name: &str) -> String {
+    if name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        name.to_string()
+    } else {
+        format!("{:?}", name)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -142,8 +328,8 @@ mod tests {
     impl MessageExchange for Exchange {
         fn pull(&mut self) {
             if let Some(receiver) = &self.receiver {
-                for message in receiver.try_iter() {
-                    self.messages.push(message)
+                for envelope in receiver.drain() {
+                    self.messages.push(envelope.message)
                 }
             }
         }
@@ -275,7 +461,7 @@ mod tests {
         sender
             .send(Message::StateChanged(TestState::Initialized))
             .unwrap();
-        assert!(receiver.try_recv().is_err());
+        assert!(receiver.drain().is_empty());
 
         // move the message through the chain
         for obj in objs.iter_mut() {
@@ -284,8 +470,58 @@ mod tests {
         }
 
         // receive it
-        let m = receiver.try_recv();
-        assert!(m.is_ok());
-        assert_eq!(m.unwrap(), Message::StateChanged(TestState::Initialized));
+        let m: Vec<Message> = receiver
+            .drain()
+            .into_iter()
+            .map(|envelope| envelope.message)
+            .collect();
+        assert_eq!(m, vec![Message::StateChanged(TestState::Initialized)]);
+    }
+
+    #[test]
+    fn test_validate() {
+        struct TestWiring;
+
+        let mut wiring = TheChannelWiring::<Message>::default();
+        wiring.wire::<Obj1, Obj2>();
+        wiring.wire::<Obj1, Obj3>();
+        wiring.expect_receiver::<Obj1>();
+        wiring.channels::<Obj1>();
+        wiring.channels::<Obj2>();
+        wiring.channels::<TestWiring>();
+        let error = wiring.validate().unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "the message wiring isn't valid: \
+             Obj1 has no receiver, nothing is wired to send to it; \
+             Obj3 is sent messages by Obj1 but never took its receiver; \
+             TestWiring took its channels but isn't wired to anything"
+        );
+
+        let mut wiring = TheChannelWiring::<Message>::default();
+        wiring.wire::<TestWiring, Obj1>();
+        wiring.wire::<Obj1, Obj2>();
+        wiring.wire::<Obj1, Obj3>();
+        let error = wiring.validate().unwrap_err().to_string();
+        assert!(error.contains("Obj1 sends to Obj2 and Obj3 but never took its senders"));
+        for _ in 0..3 {
+            wiring.channels::<Obj1>();
+        }
+        wiring.channels::<Obj2>();
+        wiring.channels::<Obj3>();
+        wiring.channels::<TestWiring>();
+        assert!(wiring.validate().is_ok());
+    }
+
+    #[test]
+    fn test_dot() {
+        let mut wiring = TheChannelWiring::<Message>::default();
+        wiring.wire::<Obj1, Obj2>();
+        wiring.wire::<Vec<Obj2>, Obj1>();
+        wiring.channels::<Obj3>();
+        assert_eq!(
+            wiring.to_dot(),
+            "digraph wiring {\n    Obj3;\n    Obj1 -> Obj2;\n    \"Vec<Obj2>\" -> Obj1;\n}\n"
+        );
     }
 }
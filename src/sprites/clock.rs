@@ -0,0 +1,35 @@
+use std::time::{Duration, Instant};
+
+/**
+ * Clock is where the game gets the time from. It is monotonic, the time never goes backwards, and it is
+ * injected so that each front end can use whatever its platform provides and tests can control the time.
+ */
+pub trait Clock {
+    /// The time since some fixed point, such as when the clock was created.
+    fn now(&self) -> Duration;
+}
+
+/// A Clock on std::time::Instant, for the platforms which have one.
+pub struct InstantClock {
+    start: Instant,
+}
+
+impl InstantClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for InstantClock {
+    fn default() -> Self {
+        InstantClock::new()
+    }
+}
+
+impl Clock for InstantClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
@@ -0,0 +1,79 @@
+use std::cell::RefCell;
+
+use crate::sprites::Rect;
+
+// past this many rects they're merged into one around all of them, which is cheaper to keep track of than to draw
+const MAX_RECTS: usize = 32;
+
+/**
+ * Damage is the parts of the window which have been drawn since the frame was last shown. The sprites only draw
+ * what has changed since they were last rendered, so a RendererContext adds whatever it draws here, and a frame
+ * with no damage doesn't need to be shown at all.
+ */
+#[derive(Debug, Default)]
+pub struct Damage {
+    rects: RefCell<Vec<Rect>>,
+}
+
+impl Damage {
+    /// Add a rect which was drawn, nothing is added when it's inside one which already was.
+    pub fn add(&self, rect: Rect) {
+        let mut rects = self.rects.borrow_mut();
+        if rects.iter().any(|damaged| damaged.contains_rect(rect)) {
+            return;
+        }
+        rects.retain(|damaged| !rect.contains_rect(*damaged));
+        rects.push(rect);
+        if rects.len() > MAX_RECTS {
+            let bounds = rects
+                .iter()
+                .fold(rect, |bounds, damaged| bounds.union(*damaged));
+            *rects = vec![bounds];
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.borrow().is_empty()
+    }
+
+    /// The rects which were drawn, without taking them.
+    pub fn rects(&self) -> Vec<Rect> {
+        self.rects.borrow().clone()
+    }
+
+    /// Take the rects which were drawn, once the frame has been shown.
+    pub fn take(&self) -> Vec<Rect> {
+        self.rects.replace(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Damage, MAX_RECTS};
+    use crate::sprites::Rect;
+
+    #[test]
+    fn test_add() {
+        let damage = Damage::default();
+        assert!(damage.is_empty());
+        damage.add(Rect::new(10, 10, 5, 5));
+        damage.add(Rect::new(11, 11, 2, 2));
+        assert_eq!(damage.rects(), vec![Rect::new(10, 10, 5, 5)]);
+
+        // a rect around the ones already there replaces them
+        damage.add(Rect::new(30, 0, 5, 5));
+        damage.add(Rect::new(0, 0, 20, 20));
+        assert_eq!(
+            damage.take(),
+            vec![Rect::new(30, 0, 5, 5), Rect::new(0, 0, 20, 20)]
+        );
+        assert!(damage.is_empty());
+
+        // too many are merged
+        for index in 0..=MAX_RECTS as i32 {
+            damage.add(Rect::new(index * 10, 5, 5, 5));
+        }
+        let right = MAX_RECTS as i32 * 10 + 5;
+        assert_eq!(damage.take(), vec![Rect::new(0, 5, right as u32, 5)]);
+    }
+}
@@ -3,12 +3,13 @@ use crate::sprites::render_digit;
 use crate::sprites::Error;
 use crate::sprites::GameState;
 use crate::sprites::{ChannelMessage, ChannelWiring, Exchange, MessageExchange};
-use crate::sprites::{Renderer, RendererContext, Sprite};
+use crate::sprites::{RendererContext, Sprite};
 use std::rc::Rc;
 
 pub struct FlagCounter {
     layout: Layout,
-    flags: i16,
+    flags: i32,
+    shown: Option<i32>,
     exchange: Exchange,
 }
 
@@ -17,12 +18,18 @@ impl FlagCounter {
         FlagCounter {
             layout: layout,
             flags: layout.options.mines(),
+            shown: None,
             exchange: Exchange::new_from_wiring::<FlagCounter>(wiring),
         }
     }
 
-    fn render(&self, context: &Box<dyn RendererContext>) -> Result<(), Error> {
+    // the counter is only drawn again when the flags have changed
+    fn render(&mut self, context: &Box<dyn RendererContext>) -> Result<(), Error> {
         let value = self.flags;
+        if self.shown == Some(value) {
+            return Ok(());
+        }
+        self.shown = Some(value);
         let image = context.load("digit_panel")?;
         let bounding_box = context.layout().flag_digit_panel();
         context.render_image(&image, None, bounding_box)?;
@@ -46,20 +53,11 @@ impl MessageExchange for FlagCounter {
                 ChannelMessage::GameStateChanged(GameState::Init) => {
                     self.flags = self.layout.options.mines()
                 }
-                ChannelMessage::Flagged(true) => {
-                    self.flags -= 1;
-                    if self.flags == 0 {
-                        self.exchange
-                            .push_message(ChannelMessage::FlagStateChanged(true));
-                    }
-                }
-                ChannelMessage::Flagged(false) => {
-                    self.flags += 1;
-                    if self.flags == 1 {
-                        self.exchange
-                            .push_message(ChannelMessage::FlagStateChanged(false));
-                    }
-                }
+                // the mines which are left are flagged when the game is won
+                ChannelMessage::GameStateChanged(GameState::Win) => self.flags = 0,
+                ChannelMessage::Flagged(true) => self.flags -= 1,
+                ChannelMessage::Flagged(false) => self.flags += 1,
+                ChannelMessage::Resume(data) => self.flags = data.flags_remaining,
                 ChannelMessage::Render(context) => self.render(&context).unwrap(),
                 _ => (),
             }
@@ -1,195 +1,282 @@
-use std::collections::BTreeSet;
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::cell::RefCell;
+use std::rc::Rc;
 
 use crate::config::Layout;
+use crate::model::{Board, CellView};
+use crate::solver::{Probabilities, Solver};
 use crate::sprites::GameState;
 use crate::sprites::{ChannelMessage, ChannelWiring, Exchange, MessageExchange};
-use crate::sprites::{Error, Rect};
-use crate::sprites::{Renderer, RendererContext};
+use crate::sprites::{Color, Error, Rect};
+use crate::sprites::{MouseAction, MouseButton, MouseEventData, RendererContext};
 use crate::sprites::{Sprite, Tile};
 
+const HEATMAP_ALPHA: u8 = 128;
+
+/**
+ * Grid is the sprite for the board. It translates mouse events into moves on the Board, lets the Button and
+ * FlagCounter know when the game state or the flags change, and renders a Tile for every cell.
+ *
+ * The heatmap tints the hidden tiles by the chance that they're a mine, from green for safe to red for a mine.
+ * The probabilities are only worked out again when something has changed since the last render.
+ *
+ * A hint highlights a tile the solver is sure is safe, until the next move. When there isn't one, the tile least
+ * likely to be a mine is revealed. Either way the board counts the hint.
+ *
+ * A left or middle press only happens when the button is released over the tile it was pressed on. Until then
+ * the pressed tile, or for a chord the tile and its neighbors, are drawn pushed in while the mouse is over it.
+ * Flags, and question marks, go on as soon as the right button is pressed.
+ *
+ * Each render only draws the tiles which look different from the last time they were drawn.
+ */
 pub struct Grid {
     layout: Layout,
     bounding_box: Rect,
     tiles: Vec<Tile>,
-    minefield: Minefield,
+    board: Rc<RefCell<Board>>,
     exchange: Exchange,
+    heatmap: bool,
+    probabilities: Option<Probabilities>,
+    hint: Option<u32>,
+    pressed: Option<(u32, MouseButton)>,
+    over: bool,
+    shown: Vec<Option<Look>>,
+}
+
+// how a tile is drawn: the cell, whether it's highlighted and its tint on the heatmap
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Look {
+    view: CellView,
+    highlight: bool,
+    tint: Option<Color>,
+}
+
+// the tint for a tile with the probability of being a mine
+fn heat(probability: f64) -> Color {
+    let red = (probability.max(0.0).min(1.0) * 255.0).round() as u8;
+    Color {
+        r: red,
+        g: 255 - red,
+        b: 0,
+        a: HEATMAP_ALPHA,
+    }
 }
 
 impl Grid {
-    pub fn new(layout: Layout, wiring: &mut ChannelWiring) -> Self {
-        let mut exchange = Exchange::new_from_wiring::<Grid>(wiring);
+    pub fn new(layout: Layout, board: Rc<RefCell<Board>>, wiring: &mut ChannelWiring) -> Self {
+        let exchange = Exchange::new_from_wiring::<Grid>(wiring);
         let bounding_box = layout.grid();
-        let mut minefield = Minefield::new(layout);
-        minefield.reset();
-        let (mut senders, tiles) = Grid::build_tiles(layout, &minefield, &exchange);
-        exchange.replace_senders(&mut senders);
+        let tiles = (0..layout.options.tiles() as u32)
+            .map(|index| Tile::new(layout.grid_tile(index)))
+            .collect();
 
         Self {
             layout: layout,
             bounding_box: bounding_box,
             tiles: tiles,
-            minefield: minefield,
+            board: board,
             exchange: exchange,
+            heatmap: false,
+            probabilities: None,
+            hint: None,
+            pressed: None,
+            over: false,
+            shown: vec![None; layout.options.tiles() as usize],
         }
     }
 
-    fn build_tiles(
-        layout: Layout,
-        minefield: &Minefield,
-        exchange: &Exchange,
-    ) -> (Vec<Sender<ChannelMessage>>, Vec<Tile>) {
-        struct TileChannel {
-            sender: Sender<ChannelMessage>,
-            receiver: Option<Receiver<ChannelMessage>>,
+    // the index of the tile under the mouse
+    fn tile_at(&self, event: &MouseEventData) -> Option<u32> {
+        if !self.bounding_box.contains_point((event.x, event.y)) {
+            return None;
         }
+        let column = (event.x - self.bounding_box.left()) / Layout::tile_side() as i32;
+        let row = (event.y - self.bounding_box.top()) / Layout::tile_side() as i32;
+        Some(self.layout.options.index(row as i16, column as i16))
+    }
 
-        impl TileChannel {
-            pub fn get_receiver(&mut self) -> Option<Receiver<ChannelMessage>> {
-                use std::mem::swap;
-                let mut opt: Option<Receiver<ChannelMessage>> = None;
-                swap(&mut self.receiver, &mut opt);
-                opt
+    fn handle_mouse_action(&mut self, event: &MouseEventData) {
+        let tile = self.tile_at(event);
+        let playing = matches!(
+            self.board.borrow().state(),
+            GameState::Init | GameState::Playing
+        );
+        match (event.action, event.mouse_btn, tile) {
+            (MouseAction::Click, button, Some(index))
+            | (MouseAction::Press, button @ MouseButton::Right, Some(index)) => {
+                let (row, column) = self.layout.options.row_column(index);
+                self.handle_mouse_event(row, column, button);
             }
-
-            pub fn clone_sender(&self) -> Sender<ChannelMessage> {
-                self.sender.clone()
+            (MouseAction::Press, button, Some(index)) if playing => {
+                self.pressed = Some((index, button));
+                self.over = true;
             }
-        }
-
-        impl Default for TileChannel {
-            fn default() -> Self {
-                let (sender, receiver) = channel();
-                Self {
-                    sender,
-                    receiver: Some(receiver),
+            (MouseAction::Move, _, tile) => {
+                self.over = matches!(self.pressed, Some((index, _)) if tile == Some(index));
+            }
+            (MouseAction::Release, button, tile) => {
+                if let Some((index, pressed)) = self.pressed.take() {
+                    if pressed == button && tile == Some(index) {
+                        let (row, column) = self.layout.options.row_column(index);
+                        self.handle_mouse_event(row, column, button);
+                    }
                 }
+                self.over = false;
             }
+            _ => (),
+        }
+    }
+
+    // the tiles which are drawn pushed in while a tile is pressed
+    fn pushed_in(&self) -> Vec<u32> {
+        let (index, button) = match self.pressed {
+            Some(pressed) if self.over => pressed,
+            _ => return Vec::new(),
+        };
+        let mut tiles = vec![index];
+        if button == MouseButton::Middle {
+            let options = self.layout.options;
+            options.for_each_neighbor(index, |row, column| tiles.push(options.index(row, column)));
         }
+        tiles
+    }
 
-        let tile_count = layout.options.tiles();
-        let mut tile_channels: Vec<TileChannel> = Vec::new();
-        for _ in 0..tile_count {
-            tile_channels.push(TileChannel::default());
+    fn handle_mouse_event(&mut self, row: i16, column: i16, mouse_btn: MouseButton) {
+        let mut board = self.board.borrow_mut();
+        let (state, flags) = (board.state(), board.flags());
+        match mouse_btn {
+            MouseButton::Left => match board.cell(row, column) {
+                CellView::Revealed(_) => board.chord(row, column),
+                _ => board.reveal(row, column),
+            },
+            MouseButton::Middle => board.chord(row, column),
+            MouseButton::Right => board.mark(row, column),
         }
+        // winning flags the rest of the mines, and the counter goes to zero with the state
+        if board.flags() != flags && board.state() != GameState::Win {
+            self.exchange
+                .push_message(ChannelMessage::Flagged(board.flags() > flags));
+        }
+        if board.state() != state {
+            self.exchange
+                .push_message(ChannelMessage::GameStateChanged(board.state()));
+        }
+    }
 
-        // these are the flag_state_listeners
-        // the are also the tile sprites
-        let mut senders: Vec<Sender<ChannelMessage>> = Vec::new();
-        for channel in tile_channels.iter() {
-            senders.push(channel.clone_sender());
+    fn render(&mut self, context: &Box<dyn RendererContext>) -> Result<(), Error> {
+        let board = self.board.borrow();
+        let pushed_in = self.pushed_in();
+        for (index, tile) in self.tiles.iter().enumerate() {
+            // a pushed in tile looks like an empty one
+            let view = match board.shown_at(index as u32) {
+                view if view.is_hidden() && pushed_in.contains(&(index as u32)) => {
+                    CellView::Revealed(0)
+                }
+                view => view,
+            };
+            let tint = match &self.probabilities {
+                Some(probabilities) if board.cell_at(index as u32).is_hidden() => {
+                    probabilities.get(index as u32).map(heat)
+                }
+                _ => None,
+            };
+            let look = Look {
+                view: view,
+                highlight: self.hint == Some(index as u32),
+                tint: tint,
+            };
+            if self.shown[index] == Some(look) {
+                continue;
+            }
+            // the tint is blended over the tile, so they're drawn together
+            tile.render(look.view, look.highlight, context)?;
+            if let Some(tint) = look.tint {
+                context.fill_rect(self.layout.grid_tile(index as u32), tint)?;
+            }
+            self.shown[index] = Some(look);
         }
+        Ok(())
+    }
 
-        let mut tiles: Vec<Tile> = Vec::new();
-        for index in 0..tile_count {
-            let mut neighbors = exchange.clone_senders();
-            let closure = |row, column| {
-                let index = layout.options.index(row, column) as usize;
-                let channel = &tile_channels[index];
-                neighbors.push(channel.clone_sender());
+    fn hint(&mut self) {
+        let (hint, options) = {
+            let board = self.board.borrow();
+            if !matches!(board.state(), GameState::Init | GameState::Playing) {
+                return;
+            }
+            let safe = Solver::from_board(&board).solve().safe;
+            let hint = match safe.iter().find(|index| board.cell_at(**index).is_hidden()) {
+                Some(index) => Ok(*index),
+                None => {
+                    let probabilities = Probabilities::from_board(&board);
+                    let risk = |index: &u32| probabilities.get(*index).unwrap_or(1.0);
+                    let hidden = (0..self.tiles.len() as u32)
+                        .filter(|index| board.cell_at(*index).is_hidden());
+                    match hidden.min_by(|a, b| risk(a).partial_cmp(&risk(b)).unwrap()) {
+                        Some(index) => Err(index),
+                        None => return,
+                    }
+                }
             };
-            layout.options.for_each_neighbor(index as u16, closure);
-            let bounding_box = layout.grid_tile(index);
-            let receiver = tile_channels[index as usize].get_receiver();
-            let tile_exchange = Exchange::new(neighbors, receiver);
-            let mut tile = Tile::new(tile_exchange, bounding_box);
-            let adjacent_mines = minefield.adjacent_mines(index as u16);
-            let is_mine = minefield.mine_at(index);
-            tile.reset(is_mine, adjacent_mines);
-            tiles.push(tile);
+            (hint, *board.options())
+        };
+        self.board.borrow_mut().add_hint();
+        match hint {
+            Ok(safe) => self.hint = Some(safe),
+            Err(least_likely) => {
+                let (row, column) = options.row_column(least_likely);
+                self.handle_mouse_event(row, column, MouseButton::Left);
+            }
+        }
+    }
+
+    // the probabilities are shown while the game is being played
+    fn update_heatmap(&mut self) {
+        let board = self.board.borrow();
+        let playing = matches!(board.state(), GameState::Init | GameState::Playing);
+        if !self.heatmap || !playing {
+            self.probabilities = None;
+        } else if self.probabilities.is_none() {
+            self.probabilities = Some(Probabilities::from_board(&board));
         }
-        (senders, tiles)
     }
 }
 
 impl MessageExchange for Grid {
     fn pull(&mut self) -> u32 {
-        let mut count = self.exchange.pull();
+        let count = self.exchange.pull();
         for message in self.exchange.get_messages().iter() {
+            // anything but a render or the mouse moving can change the board
+            let moving = match message {
+                ChannelMessage::MouseEvent(event) => event.action == MouseAction::Move,
+                _ => false,
+            };
+            if !moving && !matches!(message, ChannelMessage::Render(_)) {
+                self.probabilities = None;
+            }
+            // the hint is only until the next move
+            if let ChannelMessage::MouseEvent(_) | ChannelMessage::Resume(_) = message {
+                if !moving {
+                    self.hint = None;
+                }
+            }
             match message {
                 ChannelMessage::GameStateChanged(GameState::Init) => {
-                    self.minefield.reset();
-                    for index in 0..self.tiles.len() {
-                        let is_mine = self.minefield.mine_at(index as i16);
-                        let adjacent_mines = self.minefield.adjacent_mines(index as u16);
-                        self.tiles[index].reset(is_mine, adjacent_mines);
-                    }
-                    self.exchange.push_message(message.clone());
+                    self.hint = None;
+                    self.pressed = None;
+                    self.board.borrow_mut().reset();
                 }
-                ChannelMessage::GameStateChanged(_state) => {
-                    self.exchange.push_message(message.clone())
-                }
-                ChannelMessage::FlagStateChanged(_exhausted) => {
-                    self.exchange.push_message(message.clone())
-                }
-                ChannelMessage::Render(_) => self.exchange.push_message(message.clone()),
-                ChannelMessage::MouseEvent(event) => {
-                    if self.bounding_box.contains_point((event.x, event.y)) {
-                        let column = (event.x - self.bounding_box.left()) / Layout::tile_side() as i32;
-                        let row = (event.y - self.bounding_box.top()) / Layout::tile_side() as i32;
-                        let index = self.layout.options.index(row as i16, column as i16) as usize;
-                        self.exchange.push_message_to_index(message.clone(), index as usize);
-                    }
+                ChannelMessage::Render(context) => {
+                    self.update_heatmap();
+                    self.render(&context).unwrap()
                 }
+                ChannelMessage::ToggleHeatmap => self.heatmap = !self.heatmap,
+                ChannelMessage::Hint => self.hint(),
+                ChannelMessage::MouseEvent(event) => self.handle_mouse_action(event),
                 _ => (),
             }
         }
-        for tiles in self.tiles.iter_mut() {
-            count += tiles.pull();
-        }
         count
     }
 }
 
-
 impl Sprite for Grid {}
-
-struct Minefield {
-    layout: Layout,
-    mines: BTreeSet<i16>,
-}
-
-use rand::prelude::*;
-
-impl Minefield {
-    fn new(layout: Layout) -> Self {
-        let mut obj = Self {
-            layout: layout,
-            mines: BTreeSet::new(),
-        };
-        obj.reset();
-        obj
-    }
-
-    fn mine_at(&self, index: i16) -> bool {
-        self.mines.contains(&index)
-    }
-
-    fn adjacent_mines(&self, index: u16) -> u8 {
-        let mut sum = 0;
-        let closure = |row, column| {
-            let index = self.layout.options.index(row, column);
-            if self.mine_at(index as i16) {
-                sum += 1;
-            }
-        };
-        self.layout.options.for_each_neighbor(index, closure);
-        sum
-    }
-
-    fn reset(&mut self) {
-        self.mines.clear();
-        self.place_mines();
-    }
-
-    fn place_mines(&mut self) {
-        let max_index = self.layout.options.tiles();
-        let mine_count = self.layout.options.mines() as usize;
-        let mut rng = rand::thread_rng();
-
-        while self.mines.len() < mine_count {
-            let index = rng.gen_range(0, max_index);
-            self.mines.insert(index);
-        }
-    }
-}
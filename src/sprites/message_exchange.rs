@@ -1,8 +1,11 @@
+use std::any::TypeId;
+use std::fmt;
 use std::mem::swap;
-use std::sync::mpsc::{Receiver, Sender};
+use crate::sprites::queue::{Receiver, Sender};
+use crate::sprites::{Trace, TraceEvent};
 
 use crate::sprites::GameState;
-use crate::sprites::{RendererContext, MouseEventData};
+use crate::sprites::{RendererContext, MouseEventData, ResumeData};
 
 pub trait MessageExchange {
     fn pull(&mut self) -> u32 {
@@ -17,14 +20,30 @@ use std::rc::Rc;
 pub enum ChannelMessage {
     TestMessage,                 //
     GameStateChanged(GameState), //< Game state change
-    FlagStateChanged(bool),      //< Flag state change, either exhausted or not
     Flagged(bool),               //< Tile has been flagged (true) or unflagged(false)
-    Revealed(bool, bool),        //< Tile has been revealed and is_mine, has_adjacent_mines
-    Clear, //< Trying to clear an area of mines, neigbors use this to determine if they can reveal
     Render(Rc<Box<dyn RendererContext + 'static>>),
     MouseEvent(MouseEventData),
+    Resume(ResumeData),          //< A saved game has been restored
+    ToggleHeatmap,               //< Show or hide the mine probabilities over the hidden tiles
+    Hint,                        //< Highlight a safe tile, or reveal the least likely mine if there isn't one
 
 }
+// the rendering context can't be printed, so a render is just its name
+impl fmt::Debug for ChannelMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelMessage::TestMessage => f.write_str("TestMessage"),
+            ChannelMessage::GameStateChanged(state) => write!(f, "GameStateChanged({:?})", state),
+            ChannelMessage::Flagged(flagged) => write!(f, "Flagged({:?})", flagged),
+            ChannelMessage::Render(_) => f.write_str("Render"),
+            ChannelMessage::MouseEvent(event) => write!(f, "MouseEvent({:?})", event),
+            ChannelMessage::Resume(data) => write!(f, "Resume({:?})", data),
+            ChannelMessage::ToggleHeatmap => f.write_str("ToggleHeatmap"),
+            ChannelMessage::Hint => f.write_str("Hint"),
+        }
+    }
+}
+
 impl Default for ChannelMessage {
     fn default() -> Self {
         ChannelMessage::TestMessage
@@ -38,6 +57,8 @@ pub struct Exchange {
     messages: Vec<ChannelMessage>,
     senders: Vec<Sender<ChannelMessage>>,
     receiver: Option<Receiver<ChannelMessage>>,
+    owner: Option<TypeId>,
+    trace: Trace,
 }
 
 impl Exchange {
@@ -45,75 +66,57 @@ impl Exchange {
     where
         T: 'static,
     {
+        // the exchange is for a type which is sent messages, the wiring reports it if none are
+        wiring.expect_receiver::<T>();
         let (senders, receiver) = wiring.channels::<T>();
-        if receiver.is_none() {
-            println!("No receiver found")
-        }
         Self {
             messages: Vec::new(),
             senders: senders.unwrap_or_default(),
             receiver: receiver,
-        }
-    }
-    pub fn new(
-        senders: Vec<Sender<ChannelMessage>>,
-        receivers: Option<Receiver<ChannelMessage>>,
-    ) -> Self {
-        Self {
-            messages: Vec::new(),
-            senders: senders,
-            receiver: receivers,
+            owner: Some(TypeId::of::<T>()),
+            trace: wiring.trace().clone(),
         }
     }
    
     pub fn push_message(&self, message: ChannelMessage) {
         for tx in self.senders.iter() {
-            tx.send(message.clone()).unwrap();
+            let sequence = tx.send(message.clone()).unwrap();
+            self.trace
+                .record(TraceEvent::Sent, sequence, self.owner, tx.receiver(), &message);
         }
     }
 
-    pub fn push_message_to_index(&self, message: ChannelMessage, index: usize) {
-        self.senders[index].send(message).unwrap();
-    }
-
-    pub fn clone_senders(&self) -> Vec<Sender<ChannelMessage>> {
-        self.senders.clone()
-    }
-
     pub fn get_messages(&mut self) -> Vec<ChannelMessage> {
         let mut messages: Vec<ChannelMessage> = Vec::new();
         swap(&mut self.messages, &mut messages);
         messages
     }
-
-    pub fn replace_senders(&mut self, senders: &mut Vec<Sender<ChannelMessage>>) {
-        swap(&mut self.senders, senders);
-    }
 }
 
 impl MessageExchange for Exchange {
+    // the messages waiting are taken together, up to the end of the run the schedule is handing out
     fn pull(&mut self) -> u32 {
-        let mut count: u32 = 0;
-        if let Some(receiver) = &self.receiver {
-            if let Ok(message) = receiver.try_recv() {
-                self.messages.push(message);
-                count = 1;
-            }
-        }
-        /*
-            for message in receiver.try_iter() {
-                self.messages.push(message);
-                count += 1
-            }
+        let envelopes = match self.receiver.as_ref() {
+            Some(receiver) => receiver.drain(),
+            None => return 0,
+        };
+        let count = envelopes.len() as u32;
+        for envelope in envelopes {
+            let (sequence, from) = (envelope.sequence, envelope.from);
+            self.trace
+                .record(TraceEvent::Received, sequence, from, self.owner, &envelope.message);
+            self.messages.push(envelope.message);
         }
-        */
         count
     }
 
     fn push(&mut self) {
         for message in self.messages.iter() {
             for tx in self.senders.iter() {
-                tx.send(message.clone());
+                if let Ok(sequence) = tx.send(message.clone()) {
+                    self.trace
+                        .record(TraceEvent::Sent, sequence, self.owner, tx.receiver(), message);
+                }
             }
         }
         self.messages.clear();
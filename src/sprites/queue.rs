@@ -0,0 +1,278 @@
+use std::any::TypeId;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+/**
+ * The queues the sprites' messages go through. The sprites all run on one thread, so rather than a
+ * std::sync::mpsc channel each receiver owns a VecDeque, and its senders hold on to it weakly. Sending pushes
+ * onto the back of it, and the receiver takes the messages waiting from the front together, in the order they
+ * were sent.
+ *
+ * Each message is numbered from the sequence as it's sent, so that it can be followed from its sender to its
+ * receiver.
+ */
+pub fn queue<M>(sequence: Rc<Cell<u64>>) -> (Sender<M>, Receiver<M>) {
+    let messages = Rc::new(RefCell::new(VecDeque::new()));
+    let sender = Sender {
+        messages: Rc::downgrade(&messages),
+        sequence: sequence,
+        schedule: None,
+        from: None,
+    };
+    let receiver = Receiver {
+        messages: messages,
+        until: None,
+    };
+    (sender, receiver)
+}
+
+/// A message waiting in a queue, with its number and the type which sent it, if it's known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope<M> {
+    pub sequence: u64,
+    pub from: Option<TypeId>,
+    pub message: M,
+}
+
+/**
+ * Schedule keeps the order messages were sent in across all of the queues made from it, as the type which
+ * receives each run of them and the number of the last message in the run. Taking the runs from the front and
+ * having each type take the messages of its run together hands every message out in the order it was sent,
+ * whichever queue it went through, so a message which causes others is handled before any of them, and they're
+ * handled before anything sent after it.
+ */
+#[derive(Clone, Default)]
+pub struct Schedule {
+    receivers: Rc<RefCell<VecDeque<(TypeId, u64)>>>,
+    sequence: Rc<Cell<u64>>,
+    until: Rc<Cell<Option<u64>>>,
+}
+
+impl Schedule {
+    /// A queue for the type, whose messages are scheduled as they're sent.
+    pub fn queue<M>(&self, receiver: TypeId) -> (Sender<M>, Receiver<M>) {
+        // the messages are numbered across all of the queues
+        let (mut sender, mut receiver_queue) = queue(self.sequence.clone());
+        sender.schedule = Some((self.clone(), receiver));
+        receiver_queue.until = Some(self.until.clone());
+        (sender, receiver_queue)
+    }
+
+    /// The type which receives the oldest run of messages, and the number of the last of them, if any are
+    /// waiting.
+    pub fn next(&self) -> Option<(TypeId, u64)> {
+        self.receivers.borrow_mut().pop_front()
+    }
+
+    /// Handle a run of messages, the receivers of the queues only take the messages numbered up to the last.
+    pub fn deliver<T>(&self, last: u64, handle: impl FnOnce() -> T) -> T {
+        self.until.set(Some(last));
+        let handled = handle();
+        self.until.set(None);
+        handled
+    }
+
+    // a message was sent to the type, which adds it to the run at the back if it's for the same type
+    fn push(&self, receiver: TypeId, sequence: u64) {
+        let mut receivers = self.receivers.borrow_mut();
+        match receivers.back_mut() {
+            Some((last_receiver, last)) if *last_receiver == receiver => *last = sequence,
+            _ => receivers.push_back((receiver, sequence)),
+        }
+    }
+}
+
+impl fmt::Debug for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Schedule {{ waiting: {} }}",
+            self.receivers.borrow().len()
+        )
+    }
+}
+
+/// The message which couldn't be sent, as its receiver has gone.
+pub struct SendError<M>(pub M);
+
+// like the mpsc one, it can be unwrapped whether or not the message can be printed
+impl<M> fmt::Debug for SendError<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SendError { .. }")
+    }
+}
+
+pub struct Sender<M> {
+    messages: Weak<RefCell<VecDeque<Envelope<M>>>>,
+    sequence: Rc<Cell<u64>>,
+    schedule: Option<(Schedule, TypeId)>,
+    from: Option<TypeId>,
+}
+
+impl<M> Sender<M> {
+    /// Send the message, returning the number it was given.
+    pub fn send(&self, message: M) -> Result<u64, SendError<M>> {
+        match self.messages.upgrade() {
+            Some(messages) => {
+                let sequence = self.sequence.get() + 1;
+                self.sequence.set(sequence);
+                messages.borrow_mut().push_back(Envelope {
+                    sequence: sequence,
+                    from: self.from,
+                    message: message,
+                });
+                if let Some((schedule, receiver)) = &self.schedule {
+                    schedule.push(*receiver, sequence);
+                }
+                Ok(sequence)
+            }
+            None => Err(SendError(message)),
+        }
+    }
+
+    /// The same sender, for the type which sends with it.
+    pub fn sent_by(mut self, sender: TypeId) -> Self {
+        self.from = Some(sender);
+        self
+    }
+
+    /// The type which receives the messages, if the queue was scheduled.
+    pub fn receiver(&self) -> Option<TypeId> {
+        self.schedule.as_ref().map(|(_, receiver)| *receiver)
+    }
+}
+
+impl<M> Clone for Sender<M> {
+    fn clone(&self) -> Self {
+        Sender {
+            messages: self.messages.clone(),
+            sequence: self.sequence.clone(),
+            schedule: self.schedule.clone(),
+            from: self.from,
+        }
+    }
+}
+
+impl<M> fmt::Debug for Sender<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Sender { .. }")
+    }
+}
+
+pub struct Receiver<M> {
+    messages: Rc<RefCell<VecDeque<Envelope<M>>>>,
+    until: Option<Rc<Cell<Option<u64>>>>,
+}
+
+impl<M> Receiver<M> {
+    /// Take all of the messages which are waiting, oldest first. While the schedule is handing out a run, only
+    /// the messages up to the end of the run are taken.
+    pub fn drain(&self) -> VecDeque<Envelope<M>> {
+        let mut messages = self.messages.borrow_mut();
+        let until = self.until.as_ref().and_then(|until| until.get());
+        let due = match until {
+            Some(last) => messages
+                .iter()
+                .take_while(|envelope| envelope.sequence <= last)
+                .count(),
+            None => messages.len(),
+        };
+        messages.drain(..due).collect()
+    }
+}
+
+impl<M> fmt::Debug for Receiver<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Receiver {{ waiting: {} }}",
+            self.messages.borrow().len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{queue, Envelope, Schedule};
+    use std::any::TypeId;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_queue() {
+        let (sender, receiver) = queue(Rc::new(Cell::new(0)));
+        let other = sender.clone();
+        sender.send(1).unwrap();
+        other.send(2).unwrap();
+        sender.send(3).unwrap();
+
+        // they're taken together, in the order they were sent, numbered from 1
+        let envelopes: Vec<Envelope<u64>> = (1..=3)
+            .map(|number| Envelope {
+                sequence: number,
+                from: None,
+                message: number,
+            })
+            .collect();
+        assert_eq!(receiver.drain(), envelopes);
+        assert!(receiver.drain().is_empty());
+
+        drop(receiver);
+        assert_eq!(sender.send(4).unwrap_err().0, 4);
+    }
+
+    #[test]
+    fn test_schedule() {
+        let schedule = Schedule::default();
+        let (first, first_receiver) = schedule.queue(TypeId::of::<u8>());
+        let (second, second_receiver) = schedule.queue(TypeId::of::<u16>());
+        let second = second.sent_by(TypeId::of::<u32>());
+        assert_eq!(second.receiver(), Some(TypeId::of::<u16>()));
+        first.send(1).unwrap();
+        second.send(2).unwrap();
+        second.send(3).unwrap();
+        first.send(4).unwrap();
+
+        // each run of messages is handed to its receiver in the order they were sent, across the queues
+        let mut handled = Vec::new();
+        while let Some((receiver, last)) = schedule.next() {
+            let messages = schedule.deliver(last, || {
+                if receiver == TypeId::of::<u8>() {
+                    first_receiver.drain()
+                } else {
+                    second_receiver.drain()
+                }
+            });
+            let run: Vec<u64> = messages.iter().map(|envelope| envelope.message).collect();
+            for envelope in messages {
+                // the numbers are shared by the queues, and say who sent them
+                assert_eq!(envelope.sequence, envelope.message);
+                let from = Some(TypeId::of::<u32>())
+                    .filter(|_| envelope.message != 1 && envelope.message != 4);
+                assert_eq!(envelope.from, from);
+            }
+            handled.push(run);
+        }
+        assert_eq!(handled, vec![vec![1], vec![2, 3], vec![4]]);
+
+        // a run only takes its own messages, not the later ones waiting in the same queue
+        first.send(5).unwrap();
+        first.send(6).unwrap();
+        second.send(7).unwrap();
+        first.send(8).unwrap();
+        assert_eq!(schedule.next(), Some((TypeId::of::<u8>(), 6)));
+        assert_eq!(schedule.deliver(6, || first_receiver.drain()).len(), 2);
+        // and outside of one everything waiting is taken
+        assert_eq!(first_receiver.drain().len(), 1);
+        assert_eq!(second_receiver.drain().len(), 1);
+
+        // a message which can't be sent isn't scheduled
+        drop(second_receiver);
+        assert!(second.send(9).is_err());
+        assert_eq!(schedule.next(), Some((TypeId::of::<u16>(), 7)));
+        assert_eq!(schedule.next(), Some((TypeId::of::<u8>(), 8)));
+        assert_eq!(schedule.next(), None);
+    }
+}
@@ -1,10 +1,12 @@
 use snafu::Snafu;
 use std::rc::Rc;
+use std::time::Duration;
 
 use crate::config::Layout;
 use crate::media_layer::Texture;
+use crate::sprites::Damage;
 
-pub use super::{Point, Rect, SystemTime};
+pub use super::{Point, Rect};
 
 pub fn render_digit(
     digit: u64,
@@ -16,26 +18,45 @@ pub fn render_digit(
     Ok(())
 }
 
-// common enums
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub enum GameState {
-    Init,
-    Playing,
-    Win,
-    Lose,
-}
+pub use crate::model::GameState;
 
 #[derive(Debug, Snafu)]
 #[snafu(visibility = "pub")]
 pub enum Error {
-    #[snafu(display("The start time {:#?} is invalid: {:#?}", start, source))]
-    StartTimeInvalid {
-        start: std::time::SystemTime,
-        //backtrace: Backtrace,
-        source: std::time::SystemTimeError,
-    },
     #[snafu(display("error: {}", desc))]
     Any { desc: String },
+    #[snafu(display(
+        "unknown level {}, it should be beginner, intermediate, expert or custom",
+        level
+    ))]
+    UnknownLevel { level: String },
+    #[snafu(display("{:?} is not a valid value for {}", value, name))]
+    InvalidArgument { name: String, value: String },
+    #[snafu(display("unknown flag {}", flag))]
+    UnknownFlag { flag: String },
+    #[snafu(display(
+        "a board needs at least one row and one column, not {}x{}",
+        rows,
+        columns
+    ))]
+    EmptyBoard { rows: i16, columns: i16 },
+    #[snafu(display(
+        "a {}x{} board is too big, it can have at most {} tiles",
+        rows,
+        columns,
+        limit
+    ))]
+    BoardTooBig { rows: i16, columns: i16, limit: i32 },
+    #[snafu(display("a board with {} tiles can have 0 to {} mines, not {}", tiles, tiles - 1, mines))]
+    MineCount { mines: i32, tiles: i32 },
+    #[snafu(display("line {} of the replay isn't valid: {:?}", line, text))]
+    InvalidReplay { line: usize, text: String },
+    #[snafu(display("the saved game isn't valid, {}", reason))]
+    InvalidSave { reason: String },
+    #[snafu(display("the board isn't valid, {}", reason))]
+    InvalidBoard { reason: String },
+    #[snafu(display("the message wiring isn't valid: {}", problems))]
+    InvalidWiring { problems: String },
 }
 
 impl std::convert::From<String> for Error {
@@ -44,6 +65,20 @@ impl std::convert::From<String> for Error {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum MouseButton {
     Left,
@@ -51,23 +86,99 @@ pub enum MouseButton {
     Right,
 }
 
+/**
+ * MouseButtons keeps track of whether the left and right buttons are held, across the down and up events, so that
+ * the front ends can turn pressing both together into a chord. The game sees a chord as a middle click.
+ */
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct MouseButtons {
+    left: bool,
+    right: bool,
+}
+
+impl MouseButtons {
+    /// A button went down, get the button the game should see.
+    pub fn press(&mut self, button: MouseButton) -> MouseButton {
+        match button {
+            MouseButton::Left => self.left = true,
+            MouseButton::Right => self.right = true,
+            MouseButton::Middle => (),
+        }
+        if self.left && self.right {
+            MouseButton::Middle
+        } else {
+            button
+        }
+    }
+
+    /// A button went up, get the button the game should see, a chord ends when either of its buttons does.
+    pub fn release(&mut self, button: MouseButton) -> MouseButton {
+        let held = match (self.held(), button) {
+            (Some(MouseButton::Middle), MouseButton::Left)
+            | (Some(MouseButton::Middle), MouseButton::Right) => MouseButton::Middle,
+            _ => button,
+        };
+        match button {
+            MouseButton::Left => self.left = false,
+            MouseButton::Right => self.right = false,
+            MouseButton::Middle => (),
+        }
+        held
+    }
+
+    /// The button the game sees as held, if any, for the events while the mouse moves.
+    pub fn held(&self) -> Option<MouseButton> {
+        match (self.left, self.right) {
+            (true, true) => Some(MouseButton::Middle),
+            (true, false) => Some(MouseButton::Left),
+            (false, true) => Some(MouseButton::Right),
+            (false, false) => None,
+        }
+    }
+}
+
+/**
+ * MouseAction is what the mouse did. A tile is pressed, then the mouse moves while the button is held, and the
+ * move only happens when the button is released over the same tile. A click is a press and release at once, as
+ * the text front end and the tests send.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MouseAction {
+    Click,
+    Press,
+    Move,
+    Release,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct MouseEventData {
     pub x: i32,
     pub y: i32,
     pub mouse_btn: MouseButton,
+    pub action: MouseAction,
 }
 
+/// How a restored game was left, so that the sprites can carry on from there.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResumeData {
+    pub state: GameState,
+    pub flags_remaining: i32,
+    pub elapsed: Duration,
+}
 
 use crate::sprites::MessageExchange;
 pub trait Sprite: MessageExchange {}
 
 pub trait RendererContext {
     fn render_image(&self, texture: &Texture, src: Option<Rect>, dst: Rect) -> Result<(), String>;
+    fn fill_rect(&self, rect: Rect, color: Color) -> Result<(), String>;
     fn layout(&self) -> &Layout;
     fn load(&self, name: &str) -> Result<Rc<Texture>, String>;
     fn load_digit(&self, value: u64) -> Result<Rc<Texture>, String>;
     fn load_tile(&self, value: u64) -> Result<Rc<Texture>, String>;
+    /// What has been drawn since the frame was last shown, anything drawn is added to it.
+    fn damage(&self) -> &Damage;
+    /// Show the frame, if anything was drawn in it.
     fn end_rendering(&self);
 }
 
@@ -76,3 +187,28 @@ pub trait Renderer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{MouseButton, MouseButtons};
+
+    #[test]
+    fn test_mouse_buttons() {
+        let mut buttons = MouseButtons::default();
+        assert_eq!(buttons.press(MouseButton::Left), MouseButton::Left);
+        assert_eq!(buttons.held(), Some(MouseButton::Left));
+        assert_eq!(buttons.release(MouseButton::Left), MouseButton::Left);
+        assert_eq!(buttons.press(MouseButton::Right), MouseButton::Right);
+
+        // the second of left and right to go down chords, whichever it is
+        assert_eq!(buttons.press(MouseButton::Left), MouseButton::Middle);
+        assert_eq!(buttons.held(), Some(MouseButton::Middle));
+        assert_eq!(buttons.release(MouseButton::Right), MouseButton::Middle);
+        assert_eq!(buttons.press(MouseButton::Right), MouseButton::Middle);
+        assert_eq!(buttons.release(MouseButton::Left), MouseButton::Middle);
+        assert_eq!(buttons.release(MouseButton::Right), MouseButton::Right);
+        assert_eq!(buttons.held(), None);
+        assert_eq!(buttons.press(MouseButton::Middle), MouseButton::Middle);
+        assert_eq!(buttons.press(MouseButton::Left), MouseButton::Left);
+    }
+}
@@ -1,43 +1,52 @@
+use std::rc::Rc;
+use std::time::Duration;
+
 use crate::sprites::render_digit;
+use crate::sprites::Clock;
 use crate::sprites::Error;
 use crate::sprites::GameState;
-use crate::sprites::{Renderer, RendererContext, Sprite};
-
-use crate::sprites::SystemTime;
+use crate::sprites::{RendererContext, Sprite};
 
 use crate::sprites::{ChannelMessage, ChannelWiring, Exchange, MessageExchange};
 
 pub struct TimeCounter {
-    elapsed: u64,
+    elapsed: Duration,
     running: bool,
-    start: SystemTime,
+    start: Duration,
+    clock: Rc<dyn Clock>,
+    shown: Option<u64>,
     exchange: Exchange,
 }
 
 impl TimeCounter {
-    pub fn new(wiring: &mut ChannelWiring) -> Self {
+    pub fn new(clock: Rc<dyn Clock>, wiring: &mut ChannelWiring) -> Self {
         Self {
-            elapsed: 0,
+            elapsed: Duration::default(),
             running: false,
-            start: SystemTime::now(),
+            start: clock.now(),
+            clock: clock,
+            shown: None,
             exchange: Exchange::new_from_wiring::<TimeCounter>(wiring),
         }
     }
 
-    fn render(&self, context_: &Box<dyn RendererContext>) -> Result<(), Error> {
-        let elapsed = if self.running {
-            self.start
-                .elapsed()
-                /*
-                .context(StartTimeInvalid {
-                    start: self.start.get(),
-                })?
-                */
-                .unwrap()
-                .as_secs()
+    /// How long the game has been played for, which carries on from a restored game.
+    pub fn elapsed(&self) -> Duration {
+        if self.running {
+            let now = self.clock.now();
+            self.elapsed + now.checked_sub(self.start).unwrap_or_default()
         } else {
             self.elapsed
-        };
+        }
+    }
+
+    // the counter is only drawn again when the seconds it shows have changed
+    fn render(&mut self, context_: &Box<dyn RendererContext>) -> Result<(), Error> {
+        let elapsed = self.elapsed().as_secs();
+        if self.shown == Some(elapsed) {
+            return Ok(());
+        }
+        self.shown = Some(elapsed);
         let image = context_.load("digit_panel")?;
         let bounding_box = context_.layout().timer_digit_panel();
         context_.render_image(&image, None, bounding_box)?;
@@ -60,19 +69,25 @@ impl MessageExchange for TimeCounter {
             match message {
                 ChannelMessage::GameStateChanged(GameState::Init) => {
                     self.running = false;
-                    self.elapsed = 0;
+                    self.elapsed = Duration::default();
                 }
                 ChannelMessage::GameStateChanged(GameState::Playing) => {
                     self.running = true;
-                    self.start = SystemTime::now();
+                    self.elapsed = Duration::default();
+                    self.start = self.clock.now();
+                }
+                ChannelMessage::Resume(data) => {
+                    self.running = data.state == GameState::Playing;
+                    self.elapsed = data.elapsed;
+                    self.start = self.clock.now();
                 }
                 ChannelMessage::GameStateChanged(GameState::Win) => {
+                    self.elapsed = self.elapsed();
                     self.running = false;
-                    self.elapsed = self.start.elapsed().unwrap().as_secs();
                 }
                 ChannelMessage::GameStateChanged(GameState::Lose) => {
+                    self.elapsed = self.elapsed();
                     self.running = false;
-                    self.elapsed = self.start.elapsed().unwrap().as_secs();
                 }
                 ChannelMessage::Render(context) => self.render(&context).unwrap(),
                 _ => (),
@@ -83,3 +98,59 @@ impl MessageExchange for TimeCounter {
 }
 
 impl Sprite for TimeCounter {}
+
+#[cfg(test)]
+mod tests {
+    use super::TimeCounter;
+    use crate::sprites::ResumeData;
+    use crate::sprites::{ChannelMessage, ChannelWiring, GameState, MessageExchange};
+    use crate::testing::TestClock;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_elapsed() {
+        let clock = Rc::new(TestClock::new(Duration::from_secs(100)));
+        struct Button;
+        let mut wiring = ChannelWiring::default();
+        wiring.wire::<Button, TimeCounter>();
+        let mut counter = TimeCounter::new(clock.clone(), &mut wiring);
+        let (senders, _) = wiring.channels::<Button>();
+        let sender = senders.unwrap().pop().unwrap();
+        let mut send = |message: ChannelMessage| {
+            sender.send(message).unwrap();
+            counter.pull();
+            counter.elapsed().as_secs()
+        };
+        let state = ChannelMessage::GameStateChanged;
+
+        // the timer doesn't run until the game does
+        clock.advance(3);
+        assert_eq!(send(state(GameState::Init)), 0);
+        assert_eq!(send(state(GameState::Playing)), 0);
+        clock.advance(7);
+        // it stops when the game is over
+        assert_eq!(send(state(GameState::Win)), 7);
+        clock.advance(11);
+        assert_eq!(send(state(GameState::Win)), 7);
+        assert_eq!(send(state(GameState::Init)), 0);
+        assert_eq!(send(state(GameState::Playing)), 0);
+        clock.advance(2);
+        assert_eq!(send(state(GameState::Lose)), 2);
+
+        // a restored game carries on from where it was left
+        let resume = |state: GameState, elapsed: u64| {
+            ChannelMessage::Resume(ResumeData {
+                state: state,
+                flags_remaining: 0,
+                elapsed: Duration::from_secs(elapsed),
+            })
+        };
+        assert_eq!(send(resume(GameState::Playing, 40)), 40);
+        clock.advance(5);
+        assert_eq!(send(ChannelMessage::TestMessage), 45);
+        assert_eq!(send(resume(GameState::Win, 12)), 12);
+        clock.advance(5);
+        assert_eq!(send(ChannelMessage::TestMessage), 12);
+    }
+}
@@ -0,0 +1,238 @@
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::Write;
+use std::rc::Rc;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    Sent,
+    Received,
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceEvent::Sent => f.write_str("sent"),
+            TraceEvent::Received => f.write_str("received"),
+        }
+    }
+}
+
+/// A message going through the exchanges, the sequence number is the same when it's sent and received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRecord {
+    pub sequence: u64,
+    pub event: TraceEvent,
+    pub sender: String,
+    pub receiver: String,
+    pub message: String,
+}
+
+impl TraceRecord {
+    /// The record as a line of JSON.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"sequence\":{},\"event\":\"{}\",\"sender\":{},\"receiver\":{},\"message\":{}}}",
+            self.sequence,
+            self.event,
+            json_string(&self.sender),
+            json_string(&self.receiver),
+            json_string(&self.message)
+        )
+    }
+}
+
+impl fmt::Display for TraceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} -> {} {}",
+            self.sequence, self.event, self.sender, self.receiver, self.message
+        )
+    }
+}
+
+/**
+ * Trace records the messages the exchanges send and receive, once it's been turned on. It keeps the last
+ * records in a ring buffer, writes them all out as JSON lines, or both. The wiring gives each exchange a clone of
+ * its trace, along with the names of the types, so that the records say who sent each message to whom.
+ *
+ * Turned off, which it is to start with, an exchange only checks that it's off.
+ */
+#[derive(Clone, Default)]
+pub struct Trace {
+    tracing: Rc<RefCell<Tracing>>,
+}
+
+#[derive(Default)]
+struct Tracing {
+    names: HashMap<TypeId, String>,
+    capacity: usize,
+    records: VecDeque<TraceRecord>,
+    writer: Option<Box<dyn Write>>,
+}
+
+impl Trace {
+    /// Keep the last capacity records, 0 stops keeping them.
+    pub fn keep(&self, capacity: usize) {
+        let mut tracing = self.tracing.borrow_mut();
+        tracing.capacity = capacity;
+        while tracing.records.len() > capacity {
+            tracing.records.pop_front();
+        }
+    }
+
+    /// Write every record to the writer as a line of JSON, e.g. to a file.
+    pub fn write_to(&self, writer: Box<dyn Write>) {
+        self.tracing.borrow_mut().writer = Some(writer);
+    }
+
+    /// Stop writing the records, flushing what was written.
+    pub fn stop_writing(&self) {
+        if let Some(mut writer) = self.tracing.borrow_mut().writer.take() {
+            let _result = writer.flush();
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        let tracing = self.tracing.borrow();
+        tracing.capacity > 0 || tracing.writer.is_some()
+    }
+
+    /// The records which were kept, oldest first.
+    pub fn records(&self) -> Vec<TraceRecord> {
+        self.tracing.borrow().records.iter().cloned().collect()
+    }
+
+    /// Give a type a name for the records.
+    pub fn name(&self, id: TypeId, name: &str) {
+        self.tracing.borrow_mut().names.insert(id, name.to_string());
+    }
+
+    /// Record a message being sent or received, if the trace is on.
+    pub fn record(
+        &self,
+        event: TraceEvent,
+        sequence: u64,
+        sender: Option<TypeId>,
+        receiver: Option<TypeId>,
+        message: &dyn fmt::Debug,
+    ) {
+        if !self.is_on() {
+            return;
+        }
+        let mut tracing = self.tracing.borrow_mut();
+        let name = |id: Option<TypeId>| {
+            id.and_then(|id| tracing.names.get(&id))
+                .map_or("?".to_string(), String::clone)
+        };
+        let record = TraceRecord {
+            sequence: sequence,
+            event: event,
+            sender: name(sender),
+            receiver: name(receiver),
+            message: format!("{:?}", message),
+        };
+        if let Some(writer) = tracing.writer.as_mut() {
+            let _result = writeln!(writer, "{}", record.to_json());
+        }
+        if tracing.capacity > 0 {
+            if tracing.records.len() == tracing.capacity {
+                tracing.records.pop_front();
+            }
+            tracing.records.push_back(record);
+        }
+    }
+}
+
+impl fmt::Debug for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Trace {{ on: {} }}", self.is_on())
+    }
+}
+
+impl Drop for Tracing {
+    fn drop(&mut self) {
+        if let Some(writer) = self.writer.as_mut() {
+            let _result = writer.flush();
+        }
+    }
+}
+
+// a string as JSON, quoted with the quotes, backslashes and control characters escaped
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Trace, TraceEvent};
+    use std::any::TypeId;
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    // a writer the test can look at afterwards
+    #[derive(Clone, Default)]
+    struct Lines(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Lines {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        let trace = Trace::default();
+        let (game, grid) = (TypeId::of::<u8>(), TypeId::of::<u16>());
+        trace.name(game, "Game");
+        trace.name(grid, "Grid");
+
+        // nothing is recorded until it's turned on
+        assert!(!trace.is_on());
+        trace.record(TraceEvent::Sent, 1, Some(game), Some(grid), &"Hint");
+        assert!(trace.records().is_empty());
+
+        // the ring buffer keeps the last records
+        trace.keep(2);
+        for sequence in 2..5 {
+            trace.record(TraceEvent::Sent, sequence, Some(game), Some(grid), &"Hint");
+        }
+        let records = trace.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].sequence, 3);
+        assert_eq!(records[1].to_string(), "4 sent Game -> Grid \"Hint\"");
+
+        // and the writer gets every record, an unknown type is a ?
+        let lines = Lines::default();
+        trace.keep(0);
+        trace.write_to(Box::new(lines.clone()));
+        trace.record(TraceEvent::Received, 4, None, Some(grid), &"Hint");
+        trace.stop_writing();
+        trace.record(TraceEvent::Received, 5, None, Some(grid), &"Hint");
+        assert!(!trace.is_on());
+        assert_eq!(
+            String::from_utf8(lines.0.borrow().clone()).unwrap(),
+            "{\"sequence\":4,\"event\":\"received\",\"sender\":\"?\",\"receiver\":\"Grid\",\
+             \"message\":\"\\\"Hint\\\"\"}\n"
+        );
+    }
+}
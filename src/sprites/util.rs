@@ -52,6 +52,22 @@ impl Rect {
         let inside_x = x >= self.left() && x < self.right();
         inside_x && (y >= self.top() && y < self.bottom())
     }
+
+    /// Returns whether all of the other rectangle is inside this one.
+    pub fn contains_rect(&self, other: Rect) -> bool {
+        let inside_x = other.left() >= self.left() && other.right() <= self.right();
+        inside_x && (other.top() >= self.top() && other.bottom() <= self.bottom())
+    }
+
+    /// Returns the smallest rectangle which holds both of them.
+    pub fn union(&self, other: Rect) -> Rect {
+        let (left, top) = (self.left().min(other.left()), self.top().min(other.top()));
+        let (right, bottom) = (
+            self.right().max(other.right()),
+            self.bottom().max(other.bottom()),
+        );
+        Rect::new(left, top, (right - left) as u32, (bottom - top) as u32)
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -64,29 +80,26 @@ impl Point {
     pub fn new(x: i32, y: i32) -> Self {
         Self { x, y }
     }
-}
 
-impl From<(i32, i32)> for Point {
-    fn from((x, y): (i32, i32)) -> Point {
-        Point::new(x, y)
+    /// Returns the x-position of this point.
+    pub fn x(&self) -> i32 {
+        self.x
     }
-}
 
-#[derive(Copy, Clone, PartialEq, Eq)]
-pub struct SystemTime {
-    pub time: u64,
+    /// Returns the y-position of this point.
+    pub fn y(&self) -> i32 {
+        self.y
+    }
 }
 
-impl SystemTime {
-    pub fn now() -> Self {
-        Self {
-            time: js_sys::Date::now() as u64,
-        }
+impl From<Point> for (i32, i32) {
+    fn from(point: Point) -> (i32, i32) {
+        (point.x, point.y)
     }
+}
 
-    pub fn elapsed(&self) -> Result<std::time::Duration, std::time::SystemTimeError> {
-        Ok(std::time::Duration::from_millis(
-            js_sys::Date::now() as u64 - self.time,
-        ))
+impl From<(i32, i32)> for Point {
+    fn from((x, y): (i32, i32)) -> Point {
+        Point::new(x, y)
     }
 }
@@ -0,0 +1,55 @@
+// Helpers for the tests: a clock which only moves when the test moves it, and clicks on the tiles and buttons
+// of a game.
+use std::cell::Cell;
+use std::time::Duration;
+
+use crate::config::Layout;
+use crate::game::Game;
+use crate::sprites::{ChannelMessage, Clock, MouseAction, MouseButton, MouseEventData, Rect};
+
+pub struct TestClock {
+    now: Cell<Duration>,
+}
+
+impl TestClock {
+    pub fn new(now: Duration) -> Self {
+        Self {
+            now: Cell::new(now),
+        }
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.now.set(now);
+    }
+
+    pub fn advance(&self, seconds: u64) {
+        self.now.set(self.now.get() + Duration::from_secs(seconds));
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+/// A click on the top left of the rect.
+pub fn click_event(rect: Rect, mouse_btn: MouseButton) -> MouseEventData {
+    MouseEventData {
+        x: rect.left(),
+        y: rect.top(),
+        mouse_btn: mouse_btn,
+        action: MouseAction::Click,
+    }
+}
+
+/// Click on the rect, returning the number of messages it took.
+pub fn click_on(game: &mut Game, rect: Rect, mouse_btn: MouseButton) -> u32 {
+    game.send(ChannelMessage::MouseEvent(click_event(rect, mouse_btn)))
+}
+
+/// Click on the tile at the index of the game's grid.
+pub fn click(game: &mut Game, index: u32, mouse_btn: MouseButton) -> u32 {
+    let tile = Layout::new(*game.board().options()).grid_tile(index);
+    click_on(game, tile, mouse_btn)
+}